@@ -0,0 +1,313 @@
+use std::process::ExitCode;
+
+use crate::{App, AppStep, PKG_MANAGER_LIST, SwitchMode, display_managers, system};
+
+const USAGE: &str = "\
+Usage: de-switcher [OPTIONS]
+
+Without options the interactive TUI is started. Any option selects non-interactive
+mode, which requires --to.
+
+Options:
+  --to <PROFILE>          Target DE profile (e.g. GNOME-Desktop). Enables non-interactive mode.
+  --from <PROFILE>        Current DE profile, overrides detection. Use 'none' to install only.
+  --pkg-manager <NAME>    Package manager to use: pacman, yay or paru (default: pacman).
+  --dm <NAME>             Display manager to enable instead of the target profile's
+                          (sddm, gdm, lightdm, lxdm, ly, greetd or cosmic-greeter).
+  -o, --output <PATH>     Where to write the script (default: ./de_switcher_<from>_to_<to>.sh).
+  --no-snapshot           Do not take a snapper/timeshift snapshot before the switch.
+  --no-rollback           Do not write the companion <output>_rollback.sh script.
+  --keep-old              Install the target alongside the current DE instead of removing it,
+                          and write <output>_finalize.sh to remove it later.
+  --keep-dm               Like --keep-old, and also leave the current display manager enabled.
+  -h, --help              Print this help and exit.";
+
+pub fn print_usage() {
+    println!("{}", USAGE);
+}
+
+/// Exit code for invalid arguments or unknown profiles.
+pub const EXIT_USAGE: u8 = 2;
+/// Exit code for failures while validating or writing the output file.
+const EXIT_FAILURE: u8 = 1;
+
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub to: Option<String>,
+    pub from: Option<String>,
+    pub pkg_manager: Option<String>,
+    pub dm: Option<String>,
+    pub output: Option<String>,
+    pub no_snapshot: bool,
+    pub no_rollback: bool,
+    pub keep_old: bool,
+    pub keep_dm: bool,
+    pub help: bool,
+}
+
+impl CliArgs {
+    /// True when any generation option was given, so the TUI is skipped.
+    pub fn is_non_interactive(&self) -> bool {
+        self.to.is_some() || self.from.is_some() || self.pkg_manager.is_some() || self.dm.is_some()
+            || self.output.is_some() || self.no_snapshot || self.no_rollback || self.keep_old || self.keep_dm
+    }
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let slot = match flag.as_str() {
+            "-h" | "--help" => {
+                parsed.help = true;
+                continue;
+            }
+            "--no-snapshot" => {
+                parsed.no_snapshot = true;
+                continue;
+            }
+            "--no-rollback" => {
+                parsed.no_rollback = true;
+                continue;
+            }
+            "--keep-old" => {
+                parsed.keep_old = true;
+                continue;
+            }
+            "--keep-dm" => {
+                parsed.keep_dm = true;
+                continue;
+            }
+            "--to" => &mut parsed.to,
+            "--from" => &mut parsed.from,
+            "--pkg-manager" => &mut parsed.pkg_manager,
+            "--dm" => &mut parsed.dm,
+            "--output" | "-o" => &mut parsed.output,
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        };
+
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(v) if !v.is_empty() => v,
+            _ => return Err(format!("option '{}' requires a value", flag)),
+        };
+        *slot = Some(value);
+    }
+
+    Ok(parsed)
+}
+
+fn find_profile_index(available: &[String], name: &str) -> Option<usize> {
+    available.iter().position(|p| p.eq_ignore_ascii_case(name))
+}
+
+/// Generates and writes a switch script without entering the TUI.
+pub fn run(args: CliArgs, mut app: App) -> ExitCode {
+    if let Err(code) = configure(args, &mut app) {
+        return ExitCode::from(code);
+    }
+
+    if !app.validate_and_finalize_path() {
+        eprintln!(
+            "error: invalid output path '{}': {}",
+            app.input_buffer,
+            app.input_error.as_deref().unwrap_or("unknown error")
+        );
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    let full_path = app.generate_filename();
+    match app.write_script() {
+        Ok(()) => {
+            println!("Script written to {}", full_path);
+            if app.writes_rollback() {
+                println!("Rollback script written to {}", app.rollback_filename());
+            }
+            if app.writes_finalize() {
+                println!("Finalize script written to {}", app.finalize_filename());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: could not write script file '{}': {}", full_path, e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Applies `args` to `app`, returning the exit code to stop with on invalid arguments.
+fn configure(args: CliArgs, app: &mut App) -> Result<(), u8> {
+    let Some(to) = args.to.as_deref() else {
+        eprintln!("error: --to <PROFILE> is required in non-interactive mode\n\n{}", USAGE);
+        return Err(EXIT_USAGE);
+    };
+
+    let Some(target_index) = find_profile_index(&app.available_des, to) else {
+        eprintln!("error: unknown target profile '{}'. Available profiles: {}", to, app.available_des.join(", "));
+        return Err(EXIT_USAGE);
+    };
+    app.selected_de_index = target_index;
+
+    if let Some(from) = args.from.as_deref() {
+        match find_profile_index(&app.available_des, from) {
+            _ if from.eq_ignore_ascii_case(system::INSTALL_ONLY_PROFILE) => {
+                app.current_de_raw = from.to_string();
+                app.current_de_profile = system::INSTALL_ONLY_PROFILE.to_string();
+            }
+            Some(index) => {
+                app.current_de_raw = from.to_string();
+                app.current_de_profile = app.available_des[index].clone();
+            }
+            None => {
+                eprintln!("error: unknown source profile '{}'. Available profiles: {}", from, app.available_des.join(", "));
+                return Err(EXIT_USAGE);
+            }
+        }
+    } else if app.current_de_profile == system::UNKNOWN_PROFILE {
+        eprintln!("warning: could not detect the current DE, no packages will be removed. Pass --from <PROFILE> or --from none.");
+    }
+
+    if let Some(pkg_manager) = args.pkg_manager.as_deref() {
+        match PKG_MANAGER_LIST.iter().position(|m| *m == pkg_manager) {
+            Some(index) => app.selected_pkg_manager_index = index,
+            None => {
+                eprintln!("error: unknown package manager '{}'. Expected one of: {}", pkg_manager, PKG_MANAGER_LIST.join(", "));
+                return Err(EXIT_USAGE);
+            }
+        }
+    }
+
+    if let Some(dm) = args.dm.as_deref() {
+        match display_managers::DISPLAY_MANAGERS.iter().position(|d| d.name == dm) {
+            Some(index) => app.selected_dm_index = Some(index),
+            None => {
+                let names: Vec<&str> = display_managers::DISPLAY_MANAGERS.iter().map(|d| d.name).collect();
+                eprintln!("error: unknown display manager '{}'. Expected one of: {}", dm, names.join(", "));
+                return Err(EXIT_USAGE);
+            }
+        }
+    }
+
+    // Detection may have failed and left the app on the source picker, where the
+    // default file name is not kept up to date.
+    app.current_step = AppStep::SelectDE;
+    app.update_filename_on_de_change();
+    app.refresh_package_sets();
+    for warning in app.display_manager_warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    if !app.eos_available {
+        eprintln!("warning: eos-packagelist is not available, using built-in and config-defined package lists.");
+    }
+    if app.missing_install_list() {
+        eprintln!(
+            "error: no package list is known for '{}'. Install eos-packagelist or add packages to profiles.toml.",
+            app.available_des[app.selected_de_index]
+        );
+        return Err(EXIT_USAGE);
+    }
+
+    if args.no_snapshot {
+        app.snapshot_enabled = false;
+    }
+    if args.no_rollback {
+        app.write_rollback = false;
+    }
+    if args.keep_dm {
+        app.switch_mode = SwitchMode::AlongsideKeepDm;
+    } else if args.keep_old {
+        app.switch_mode = SwitchMode::Alongside;
+    }
+
+    if let Some(output) = args.output {
+        app.input_cursor_position = output.len();
+        app.input_buffer = output;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProfileConfig;
+    use crate::pacman::{LocalDb, SyncDb};
+    use crate::runner::fake::ScriptedRunner;
+    use crate::system::fake::FakeProbe;
+
+    fn args(list: &[&str]) -> CliArgs {
+        parse_args(list.iter().map(|a| a.to_string())).unwrap()
+    }
+
+    fn app() -> App {
+        App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), ProfileConfig::builtin(), LocalDb::default(), SyncDb::default()).unwrap()
+    }
+
+    #[test]
+    fn default_output_follows_the_profiles_after_failed_detection() {
+        let mut app = app();
+        app.current_step = AppStep::SelectSource;
+        app.input_buffer = "./de_switcher_XFCE4_to_KDE.sh".to_string();
+
+        configure(args(&["--to", "GNOME-Desktop", "--from", "none"]), &mut app).unwrap();
+        assert_eq!(app.input_buffer, "./de_switcher_install_GNOME.sh");
+    }
+
+    #[test]
+    fn parses_flags_and_values_in_both_forms() {
+        let parsed = args(&["--to=GNOME-Desktop", "--from", "none", "-o", "out.sh", "--no-snapshot", "--keep-dm"]);
+        assert_eq!(parsed.to.as_deref(), Some("GNOME-Desktop"));
+        assert_eq!(parsed.from.as_deref(), Some("none"));
+        assert_eq!(parsed.output.as_deref(), Some("out.sh"));
+        assert!(parsed.no_snapshot && parsed.keep_dm && !parsed.no_rollback);
+        assert!(parsed.is_non_interactive());
+
+        assert!(!args(&[]).is_non_interactive());
+        assert!(!args(&["--help"]).is_non_interactive());
+        assert!(args(&["--no-snapshot"]).is_non_interactive());
+    }
+
+    #[test]
+    fn rejects_unknown_arguments_and_missing_values() {
+        let parse = |list: &[&str]| parse_args(list.iter().map(|a| a.to_string()));
+        assert_eq!(parse(&["--bogus"]).unwrap_err(), "unexpected argument '--bogus'");
+        assert_eq!(parse(&["--to"]).unwrap_err(), "option '--to' requires a value");
+        assert_eq!(parse(&["--output="]).unwrap_err(), "option '--output' requires a value");
+    }
+
+    #[test]
+    fn invalid_arguments_exit_with_the_usage_code() {
+        for list in [
+            &["--no-snapshot"][..],
+            &["--to", "Enlightenment"],
+            &["--to", "GNOME-Desktop", "--from", "Enlightenment"],
+            &["--to", "GNOME-Desktop", "--pkg-manager", "pamac"],
+            &["--to", "GNOME-Desktop", "--dm", "entrance"],
+        ] {
+            assert_eq!(configure(args(list), &mut app()), Err(EXIT_USAGE), "{:?}", list);
+        }
+    }
+
+    #[test]
+    fn run_writes_the_script_or_fails_on_a_bad_path() {
+        let dir = std::env::temp_dir().join(format!("de-switcher-cli-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("switch.sh").to_string_lossy().to_string();
+
+        let code = run(args(&["--to", "GNOME-Desktop", "--from", "none", "--no-rollback", "-o", &output]), app());
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(std::fs::read_to_string(&output).unwrap().contains("# bash switch.sh"));
+
+        let missing_dir = dir.join("missing").join("switch.sh").to_string_lossy().to_string();
+        let code = run(args(&["--to", "GNOME-Desktop", "--from", "none", "-o", &missing_dir]), app());
+        assert_eq!(code, ExitCode::from(EXIT_FAILURE));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
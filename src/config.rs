@@ -0,0 +1,251 @@
+use color_eyre::{Result, eyre::eyre};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+
+use crate::{DE_DM_MAP, PROFILE_SESSIONS, SPECIAL_INSTALL_MAP, manifests};
+
+const SYSTEM_CONFIG_PATH: &str = "/etc/de-switcher/profiles.toml";
+const CONFIG_FILE_NAME: &str = "profiles.toml";
+const FALLBACK_DM: &str = "lightdm";
+
+#[derive(Debug, Clone)]
+pub struct ProfileEntry {
+    pub profile: String,
+    pub display_manager: String,
+    pub package_group: Option<String>,
+    pub display_name: String,
+    /// Packages installed for this profile when eos-packagelist is not available.
+    /// Defaults to the built-in manifest.
+    pub packages: Vec<String>,
+    /// Session file name without `.desktop`, e.g. `hyprland`.
+    pub session: Option<String>,
+    pub user_defined: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, Spanned<RawProfile>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    display_manager: Option<String>,
+    package_group: Option<String>,
+    display_name: Option<String>,
+    packages: Option<Vec<String>>,
+    session: Option<String>,
+}
+
+/// Profile-to-display-manager and install overrides, built-in defaults merged with
+/// `/etc/de-switcher/profiles.toml` and then `~/.config/de-switcher/profiles.toml`.
+#[derive(Debug, Clone)]
+pub struct ProfileConfig {
+    entries: Vec<ProfileEntry>,
+}
+
+impl ProfileConfig {
+    pub fn builtin() -> Self {
+        let mut entries: Vec<ProfileEntry> = DE_DM_MAP.iter()
+            .map(|(profile, dm)| ProfileEntry {
+                profile: profile.to_string(),
+                display_manager: dm.to_string(),
+                package_group: SPECIAL_INSTALL_MAP.get(profile).map(|g| g.to_string()),
+                display_name: profile.to_string(),
+                packages: manifests::manifest_for(profile).iter().map(|p| p.to_string()).collect(),
+                session: builtin_session(profile),
+                user_defined: false,
+            })
+            .collect();
+
+        let mut special_only: Vec<(&str, &str)> = SPECIAL_INSTALL_MAP.iter()
+            .filter(|(profile, _)| !DE_DM_MAP.iter().any(|(p, _)| p == *profile))
+            .map(|(profile, group)| (*profile, *group))
+            .collect();
+        special_only.sort();
+
+        entries.extend(special_only.into_iter().map(|(profile, group)| ProfileEntry {
+            profile: profile.to_string(),
+            display_manager: FALLBACK_DM.to_string(),
+            package_group: Some(group.to_string()),
+            display_name: profile.to_string(),
+            packages: Vec::new(),
+            session: builtin_session(profile),
+            user_defined: false,
+        }));
+
+        ProfileConfig { entries }
+    }
+
+    /// Loads the built-in defaults and merges any config files found on disk over them.
+    pub fn load() -> Result<Self> {
+        let mut config = ProfileConfig::builtin();
+
+        let mut paths = vec![PathBuf::from(SYSTEM_CONFIG_PATH)];
+        if let Some(user_path) = user_config_path() {
+            paths.push(user_path);
+        }
+
+        for path in paths.iter().filter(|p| p.is_file()) {
+            let content = fs::read_to_string(path)
+                .map_err(|e| eyre!("could not read {}: {}", path.display(), e))?;
+            config.merge_toml(&content, path)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Built-in defaults with `content` merged over them, as if read from a config file.
+    #[cfg(test)]
+    pub fn with_overrides(content: &str) -> Result<Self> {
+        let mut config = ProfileConfig::builtin();
+        config.merge_toml(content, Path::new("test.toml"))?;
+        Ok(config)
+    }
+
+    fn merge_toml(&mut self, content: &str, path: &Path) -> Result<()> {
+        let file: ConfigFile = toml::from_str(content)
+            .map_err(|e| eyre!("malformed config {}: {}", path.display(), e))?;
+
+        let mut profiles: Vec<(String, Spanned<RawProfile>)> = file.profiles.into_iter().collect();
+        profiles.sort_by_key(|(_, raw)| raw.span().start);
+
+        for (profile, spanned) in profiles {
+            let line = line_of(content, spanned.span().start);
+            let raw = spanned.into_inner();
+
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.profile == profile) {
+                if let Some(dm) = raw.display_manager {
+                    entry.display_manager = dm;
+                }
+                if raw.package_group.is_some() {
+                    entry.package_group = raw.package_group;
+                }
+                if let Some(name) = raw.display_name {
+                    entry.display_name = name;
+                }
+                if let Some(packages) = raw.packages {
+                    entry.packages = packages;
+                }
+                if raw.session.is_some() {
+                    entry.session = raw.session;
+                }
+                continue;
+            }
+
+            let Some(display_manager) = raw.display_manager else {
+                return Err(eyre!(
+                    "malformed config {}: line {}: new profile '{}' must set display_manager",
+                    path.display(), line, profile
+                ));
+            };
+
+            self.entries.push(ProfileEntry {
+                display_name: raw.display_name.unwrap_or_else(|| profile.clone()),
+                profile,
+                display_manager,
+                package_group: raw.package_group,
+                packages: raw.packages.unwrap_or_default(),
+                session: raw.session,
+                user_defined: true,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[ProfileEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, profile: &str) -> Option<&ProfileEntry> {
+        self.entries.iter().find(|e| e.profile == profile)
+    }
+
+    pub fn display_manager(&self, profile: &str) -> &str {
+        self.get(profile).map_or(FALLBACK_DM, |e| e.display_manager.as_str())
+    }
+
+    pub fn package_group(&self, profile: &str) -> Option<&str> {
+        self.get(profile).and_then(|e| e.package_group.as_deref())
+    }
+
+    pub fn packages(&self, profile: &str) -> &[String] {
+        self.get(profile).map_or(&[], |e| e.packages.as_slice())
+    }
+
+    pub fn session(&self, profile: &str) -> Option<&str> {
+        self.get(profile).and_then(|e| e.session.as_deref())
+    }
+
+    pub fn display_name<'a>(&'a self, profile: &'a str) -> &'a str {
+        self.get(profile).map_or(profile, |e| e.display_name.as_str())
+    }
+}
+
+fn builtin_session(profile: &str) -> Option<String> {
+    PROFILE_SESSIONS.iter()
+        .find(|(p, _)| *p == profile)
+        .map(|(_, session)| session.to_string())
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("de-switcher").join(CONFIG_FILE_NAME))
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_builtin_profiles_and_adds_new_ones() {
+        let config = ProfileConfig::with_overrides(r#"
+            [profiles."GNOME-Desktop"]
+            display_manager = "sddm"
+
+            [profiles.Wayfire]
+            display_manager = "greetd"
+            packages = ["wayfire"]
+        "#).unwrap();
+
+        assert_eq!(config.display_manager("GNOME-Desktop"), "sddm");
+        assert!(!config.get("GNOME-Desktop").unwrap().user_defined);
+        let wayfire = config.get("Wayfire").unwrap();
+        assert!(wayfire.user_defined);
+        assert_eq!(wayfire.display_name, "Wayfire");
+        assert_eq!(config.packages("Wayfire"), ["wayfire"]);
+    }
+
+    #[test]
+    fn new_profiles_without_a_display_manager_report_their_line() {
+        let err = ProfileConfig::with_overrides("# custom profiles\n\n[profiles.Wayfire]\npackages = [\"wayfire\"]\n")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "malformed config test.toml: line 3: new profile 'Wayfire' must set display_manager");
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_with_their_line() {
+        let err = ProfileConfig::with_overrides("[profiles.Wayfire]\ndisplay_manager = \"sddm\"\ndisplay_manger = \"gdm\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("malformed config test.toml: "), "{}", err);
+        assert!(err.contains("unknown field `display_manger`"), "{}", err);
+        assert!(err.contains("line 3"), "{}", err);
+    }
+}
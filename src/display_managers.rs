@@ -0,0 +1,118 @@
+/// What the switch script needs to know to install and enable a display manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayManager {
+    pub name: &'static str,
+    /// Packages installed for the display manager itself.
+    pub packages: &'static [&'static str],
+    /// Greeters it can use. Any one of them is enough; the first is installed if
+    /// none of them is installed or part of the target profile.
+    pub greeters: &'static [&'static str],
+    /// systemd unit enabled by the switch script.
+    pub unit: &'static str,
+    /// Extra bash run after installing, before the unit is enabled.
+    pub setup: &'static str,
+    /// Packages it does not depend on but cannot start without, e.g. the compositor
+    /// a greeter runs in.
+    pub requires: &'static [&'static str],
+    /// Whether it only works with systemd-logind managing seats.
+    pub needs_logind: bool,
+}
+
+pub const DISPLAY_MANAGERS: &[DisplayManager] = &[
+    DisplayManager {
+        name: "sddm",
+        packages: &["sddm"],
+        greeters: &[],
+        unit: "sddm.service",
+        setup: "",
+        requires: &[],
+        needs_logind: false,
+    },
+    DisplayManager {
+        name: "gdm",
+        packages: &["gdm"],
+        greeters: &[],
+        unit: "gdm.service",
+        setup: "",
+        requires: &[],
+        needs_logind: true,
+    },
+    DisplayManager {
+        name: "lightdm",
+        packages: &["lightdm"],
+        greeters: &["lightdm-gtk-greeter", "lightdm-slick-greeter", "lightdm-webkit2-greeter"],
+        unit: "lightdm.service",
+        setup: "",
+        requires: &[],
+        needs_logind: false,
+    },
+    DisplayManager {
+        name: "lxdm",
+        packages: &["lxdm"],
+        greeters: &[],
+        unit: "lxdm.service",
+        setup: "",
+        requires: &[],
+        needs_logind: false,
+    },
+    DisplayManager {
+        name: "ly",
+        packages: &["ly"],
+        greeters: &[],
+        // ly runs on a fixed TTY and takes it over from getty.
+        unit: "ly@tty2.service",
+        setup: "sudo systemctl disable getty@tty2.service 2>/dev/null\n",
+        requires: &[],
+        needs_logind: false,
+    },
+    DisplayManager {
+        name: "greetd",
+        packages: &["greetd"],
+        greeters: &["greetd-tuigreet", "greetd-regreet", "greetd-gtkgreet"],
+        unit: "greetd.service",
+        // The stock config starts agreety, which cannot pick a session.
+        setup: r#"if command -v tuigreet >/dev/null && grep -q 'agreety' /etc/greetd/config.toml; then
+    sudo sed -i 's|^command = .*|command = "tuigreet --time --remember --remember-session"|' /etc/greetd/config.toml
+fi
+"#,
+        requires: &[],
+        needs_logind: false,
+    },
+    DisplayManager {
+        name: "cosmic-greeter",
+        packages: &["cosmic-greeter"],
+        greeters: &[],
+        unit: "cosmic-greeter.service",
+        setup: "",
+        requires: &["cosmic-comp"],
+        needs_logind: false,
+    },
+];
+
+pub fn display_manager(name: &str) -> Option<&'static DisplayManager> {
+    DISPLAY_MANAGERS.iter().find(|dm| dm.name == name)
+}
+
+/// The systemd unit for `name`, assuming `<name>.service` for unknown display managers.
+pub fn unit_for(name: &str) -> String {
+    display_manager(name).map_or_else(|| format!("{}.service", name), |dm| dm.unit.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DE_DM_MAP;
+
+    #[test]
+    fn every_builtin_profile_has_a_known_display_manager() {
+        for (profile, dm) in DE_DM_MAP {
+            assert!(display_manager(dm).is_some(), "{} uses unknown display manager {}", profile, dm);
+        }
+    }
+
+    #[test]
+    fn unknown_display_managers_get_a_service_unit() {
+        assert_eq!(unit_for("ly"), "ly@tty2.service");
+        assert_eq!(unit_for("entrance"), "entrance.service");
+    }
+}
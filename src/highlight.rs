@@ -0,0 +1,183 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "do", "done", "in",
+    "function", "return", "exit", "local", "read", "echo",
+];
+
+const PRIVILEGED_COMMANDS: &[&str] = &["sudo", "systemctl", "pacman", "yay", "paru", "reboot"];
+
+const DANGER_WORDS: &[&str] = &["rm", "disable", "--force", "-Rcs", "-Rns", "-Rs", "-R"];
+
+fn comment_style() -> Style {
+    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+}
+
+fn string_style() -> Style {
+    Style::default().fg(Color::Green)
+}
+
+fn variable_style() -> Style {
+    Style::default().fg(Color::Cyan)
+}
+
+fn keyword_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn command_style() -> Style {
+    Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+}
+
+fn danger_style() -> Style {
+    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '"' | '\'' | '$' | ';' | '|' | '&' | '(' | ')' | '`')
+}
+
+fn word_style(word: &str) -> Style {
+    if DANGER_WORDS.contains(&word) {
+        danger_style()
+    } else if PRIVILEGED_COMMANDS.contains(&word) {
+        command_style()
+    } else if KEYWORDS.contains(&word) {
+        keyword_style()
+    } else {
+        Style::default().fg(Color::White)
+    }
+}
+
+/// Byte length of the variable reference starting at `rest` (which begins with `$`).
+fn variable_len(rest: &str) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    match chars.next() {
+        Some((_, '{')) => rest.find('}').map_or(rest.len(), |end| end + 1),
+        Some((_, '(')) => 2,
+        Some((_, c)) if c.is_ascii_alphabetic() || c == '_' => {
+            let end = rest[1..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'));
+            end.map_or(rest.len(), |e| e + 1)
+        }
+        Some((i, c)) if c.is_ascii_digit() || "@#?*!$-".contains(c) => i + c.len_utf8(),
+        _ => 1,
+    }
+}
+
+/// Byte length of the quoted string starting at `rest`, including both quotes.
+fn string_len(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return i + 1;
+        }
+    }
+    rest.len()
+}
+
+/// True for lines that remove packages, disable services or delete files.
+pub fn is_danger_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') || trimmed.starts_with("echo") {
+        return false;
+    }
+    trimmed.split_whitespace().any(|word| DANGER_WORDS.contains(&word))
+}
+
+/// Splits one line of bash into styled spans. Danger lines are rendered red/bold as a whole.
+pub fn highlight_line(line: &str) -> Vec<Span<'_>> {
+    if is_danger_line(line) {
+        return vec![Span::styled(line, danger_style())];
+    }
+
+    let mut spans = Vec::new();
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let rest = &line[pos..];
+        let c = rest.chars().next().unwrap_or(' ');
+        let at_word_start = pos == 0 || line[..pos].ends_with(char::is_whitespace);
+
+        let (len, style) = if c == '#' && at_word_start {
+            (rest.len(), comment_style())
+        } else if c == '"' || c == '\'' {
+            (string_len(rest, c), string_style())
+        } else if c == '$' {
+            (variable_len(rest), variable_style())
+        } else if c.is_whitespace() || !is_word_char(c) {
+            let len = rest.find(|c: char| is_word_char(c) || matches!(c, '"' | '\'' | '$' | '#'))
+                .unwrap_or(rest.len())
+                .max(c.len_utf8());
+            (len, Style::default())
+        } else {
+            let len = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+            (len, word_style(&rest[..len]))
+        };
+
+        spans.push(Span::styled(&rest[..len], style));
+        pos += len;
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (text, style) of each span.
+    fn spans(line: &str) -> Vec<(&str, Style)> {
+        highlight_line(line).into_iter()
+            .map(|span| match span.content {
+                std::borrow::Cow::Borrowed(text) => (text, span.style),
+                std::borrow::Cow::Owned(_) => unreachable!("spans borrow the line"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spans_cover_the_whole_line() {
+        let line = r#"if [ ${#PKGS[@]} -gt 0 ]; then echo "$1 'x'" # done"#;
+        let joined: String = spans(line).into_iter().map(|(text, _)| text).collect();
+        assert_eq!(joined, line);
+    }
+
+    #[test]
+    fn hash_starts_a_comment_only_at_a_word_start() {
+        assert_eq!(spans("# note"), [("# note", comment_style())]);
+        assert_eq!(spans("ls # note").last(), Some(&("# note", comment_style())));
+        assert!(spans("foo#bar").iter().all(|(_, style)| *style != comment_style()));
+    }
+
+    #[test]
+    fn array_length_is_one_variable() {
+        assert!(spans("echo ${#ARR[@]} left").contains(&("${#ARR[@]}", variable_style())));
+        assert!(spans("echo $HOME/x").contains(&("$HOME", variable_style())));
+    }
+
+    #[test]
+    fn escaped_and_unterminated_strings() {
+        assert!(spans(r#"echo "a \"b\" c" done"#).contains(&(r#""a \"b\" c""#, string_style())));
+        assert_eq!(spans(r#"echo "open"#).last(), Some(&(r#""open"#, string_style())));
+        // Backslashes do not escape inside single quotes.
+        assert!(spans(r"echo 'it\'s'").contains(&(r"'it\'", string_style())));
+    }
+
+    #[test]
+    fn detects_danger_lines() {
+        assert!(is_danger_line("    sudo pacman -Rns \"${ORPHANS[@]}\""));
+        assert!(is_danger_line("sudo pacman -Rcs foo"));
+        assert!(is_danger_line("sudo systemctl disable --force sddm"));
+        assert!(!is_danger_line("echo \"Removing with pacman -Rns...\""));
+        assert!(!is_danger_line("  # -Rcs: Remove, cascade"));
+        assert!(!is_danger_line("sudo pacman -S --needed gnome"));
+        assert_eq!(spans("sudo pacman -Rns foo"), [("sudo pacman -Rns foo", danger_style())]);
+    }
+}
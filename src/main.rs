@@ -1,4 +1,4 @@
-use color_eyre::Result;
+use color_eyre::{Result, eyre::WrapErr};
 use crossterm::{
     execute,
     event::{self, Event, KeyCode, KeyModifiers},
@@ -10,18 +10,33 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Rect, Layout, Constraint, Direction, Alignment},
     style::{Style, Color, Modifier},
-    widgets::{Block, Borders, Paragraph, List, ListItem, ListState, Clear}, 
+    widgets::{Block, BorderType, Borders, Paragraph, List, ListItem, ListState, Clear, Wrap}, 
     Frame,
     Terminal,
     text::{Line, Span}
 };
+mod cli;
+mod config;
+mod display_managers;
+mod highlight;
+mod manifests;
+mod pacman;
+mod runner;
+mod script;
+mod system;
+
 use std::io::{self, stdout};
 use std::env;
 use std::fs;
-use std::collections::HashMap;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::process::ExitCode;
 use std::path::Path; 
 
+use config::ProfileConfig;
+use pacman::{LocalDb, PackageInfo, SpaceEstimate, SyncDb};
+use runner::{CommandRunner, SystemRunner};
+use system::{DeEvidence, HostProbe, SnapshotTool, SystemProbe};
+
 const DE_DM_MAP: &[(&str, &str)] = &[
     ("KDE-Desktop", "sddm"),
     ("GNOME-Desktop", "gdm"),
@@ -32,6 +47,34 @@ const DE_DM_MAP: &[(&str, &str)] = &[
     ("LXQT-Desktop", "sddm"),
     ("LXDE-Desktop", "lightdm"),
     ("i3-Window-Manager", "lightdm"),
+    ("COSMIC-Desktop", "cosmic-greeter"),
+    ("Hyprland", "sddm"),
+    ("Sway", "greetd"),
+    ("niri", "greetd"),
+    ("river", "ly"),
+];
+
+/// Wayland compositors and tiling WMs. eos-packagelist has no profiles for them, so
+/// their packages always come from the built-in manifests or `profiles.toml`.
+const WAYLAND_PROFILES: &[&str] = &["Hyprland", "Sway", "niri", "river"];
+
+/// Session file (without `.desktop`) each profile installs into
+/// `/usr/share/xsessions` or `/usr/share/wayland-sessions`.
+const PROFILE_SESSIONS: &[(&str, &str)] = &[
+    ("KDE-Desktop", "plasma"),
+    ("GNOME-Desktop", "gnome"),
+    ("XFCE4-Desktop", "xfce"),
+    ("Cinnamon-Desktop", "cinnamon"),
+    ("MATE-Desktop", "mate"),
+    ("Budgie-Desktop", "budgie-desktop"),
+    ("LXQT-Desktop", "lxqt"),
+    ("LXDE-Desktop", "LXDE"),
+    ("i3-Window-Manager", "i3"),
+    ("COSMIC-Desktop", "cosmic"),
+    ("Hyprland", "hyprland"),
+    ("Sway", "sway"),
+    ("niri", "niri"),
+    ("river", "river"),
 ];
 
 lazy_static::lazy_static! {
@@ -43,96 +86,295 @@ lazy_static::lazy_static! {
     };
 }
 
-const PKG_MANAGER_LIST: &[&str] = &["pacman", "yay", "paru"];
+pub const PKG_MANAGER_LIST: &[&str] = &["pacman", "yay", "paru"];
+
+/// Column titles of the package diff screen, in the order of [`App::diff_columns`].
+pub const DIFF_COLUMN_TITLES: [&str; 3] = ["Removed", "Installed", "Kept (shared)"];
+
+/// Known `XDG_CURRENT_DESKTOP`, `DESKTOP_SESSION` and session file names (lowercase)
+/// and the profile each one belongs to.
+const DE_ALIASES: &[(&str, &str)] = &[
+    ("kde", "KDE-Desktop"),
+    ("plasma", "KDE-Desktop"),
+    ("plasmawayland", "KDE-Desktop"),
+    ("plasmax11", "KDE-Desktop"),
+    ("gnome", "GNOME-Desktop"),
+    ("ubuntu:gnome", "GNOME-Desktop"),
+    ("gnome-xorg", "GNOME-Desktop"),
+    ("gnome-wayland", "GNOME-Desktop"),
+    ("gnome-classic", "GNOME-Desktop"),
+    ("xfce", "XFCE4-Desktop"),
+    ("xfce4", "XFCE4-Desktop"),
+    ("x-cinnamon", "Cinnamon-Desktop"),
+    ("cinnamon", "Cinnamon-Desktop"),
+    ("cinnamon2d", "Cinnamon-Desktop"),
+    ("cinnamon-wayland", "Cinnamon-Desktop"),
+    ("mate", "MATE-Desktop"),
+    ("budgie:gnome", "Budgie-Desktop"),
+    ("budgie", "Budgie-Desktop"),
+    ("budgie-desktop", "Budgie-Desktop"),
+    ("lxqt", "LXQT-Desktop"),
+    ("lxde", "LXDE-Desktop"),
+    ("i3", "i3-Window-Manager"),
+    ("i3-with-shmlog", "i3-Window-Manager"),
+    ("cosmic", "COSMIC-Desktop"),
+    ("hyprland", "Hyprland"),
+    ("sway", "Sway"),
+    ("niri", "niri"),
+    ("river", "river"),
+];
+
+/// Maps a raw DE name to a known profile. The whole value is tried first (so
+/// `Budgie:GNOME` is Budgie, not GNOME), then each `:` segment from the last one.
+/// Names not in [`DE_ALIASES`] must match a profile or display name exactly,
+/// ignoring case, so ambiguous prefixes such as `LX` stay unknown.
+fn map_raw_de_to_profile(raw_de: &str, profiles: &ProfileConfig) -> String {
+    let raw = raw_de.trim().to_lowercase();
+    let candidates = std::iter::once(raw.as_str()).chain(raw.rsplit(':'));
+
+    for candidate in candidates.filter(|c| !c.is_empty()) {
+        let alias = DE_ALIASES.iter()
+            .find(|(name, profile)| *name == candidate && profiles.get(profile).is_some());
+        if let Some((_, profile)) = alias {
+            return profile.to_string();
+        }
 
-fn map_raw_de_to_profile(raw_de: &str) -> String {
-    match raw_de.to_uppercase().as_str() {
-        "COSMIC" => return "COSMIC-Desktop".to_string(),
-        "I3" => return "i3-Window-Manager".to_string(),
-        _ => {}
+        let entry = profiles.entries().iter()
+            .find(|e| {
+                e.profile.eq_ignore_ascii_case(candidate)
+                    || e.display_name.eq_ignore_ascii_case(candidate)
+                    || e.session.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(candidate))
+            });
+        if let Some(entry) = entry {
+            return entry.profile.clone();
+        }
     }
 
-    DE_DM_MAP.iter()
-        .find(|(profile, _)| profile.starts_with(&raw_de.to_uppercase()))
-        .map(|(profile, _)| profile.to_string())
-        .unwrap_or_else(|| "Unknown-Desktop".to_string())
+    system::UNKNOWN_PROFILE.to_string()
 }
 
-fn get_available_des() -> Result<Vec<String>> {
-    let output = Command::new("eos-packagelist")
-        .arg("--list")
-        .output()?;
+fn get_available_des(runner: &dyn CommandRunner, profiles: &ProfileConfig) -> Result<Vec<String>> {
+    let output = runner.run("eos-packagelist", &["--list"])
+        .wrap_err("could not run eos-packagelist")?;
     
-    if !output.status.success() {
-        return Ok(DE_DM_MAP.iter().map(|(d, _)| d.to_string()).collect());
+    let fallback = || profiles.entries().iter().map(|e| e.profile.clone()).collect();
+
+    if !output.success {
+        return Ok(fallback());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    let available_des: Vec<String> = stdout.lines()
+    let mut available_des: Vec<String> = output.stdout.lines()
         .filter(|line| {
             let line = line.trim();
             line.ends_with("-Desktop") || line.ends_with("-Window-Manager") || line.contains("i3")
+                || WAYLAND_PROFILES.contains(&line)
         })
         .map(|line| line.trim().to_string())
         .collect();
 
     if available_des.is_empty() {
-        Ok(DE_DM_MAP.iter().map(|(d, _)| d.to_string()).collect())
-    } else {
-        Ok(available_des)
+        return Ok(fallback());
+    }
+
+    // Wayland profiles and profiles defined only in a config file (e.g. Wayfire) are
+    // not known to eos-packagelist.
+    let extra = profiles.entries().iter()
+        .filter(|e| e.user_defined || WAYLAND_PROFILES.contains(&e.profile.as_str()));
+    for entry in extra {
+        if !available_des.contains(&entry.profile) {
+            available_des.push(entry.profile.clone());
+        }
+    }
+
+    Ok(available_des)
+}
+
+fn get_profile_packages(runner: &dyn CommandRunner, profile: &str) -> Result<Vec<String>> {
+    let output = runner.run("eos-packagelist", &[profile])
+        .wrap_err("could not run eos-packagelist")?;
+
+    if !output.success {
+        return Ok(Vec::new());
+    }
+
+    Ok(output.stdout.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(|line| line.split_whitespace())
+        .map(|pkg| pkg.to_string())
+        .collect())
+}
+
+/// Whether the switch script removes the current DE or installs the target next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchMode {
+    Replace,
+    /// Keep the current DE; a finalize script removes it later.
+    Alongside,
+    /// Like `Alongside`, and also keep the current display manager enabled.
+    AlongsideKeepDm,
+}
+
+impl SwitchMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            SwitchMode::Replace => "replace the current DE",
+            SwitchMode::Alongside => "install alongside the current DE",
+            SwitchMode::AlongsideKeepDm => "install alongside, keep the current display manager",
+        }
     }
 
+    fn next(self) -> Self {
+        match self {
+            SwitchMode::Replace => SwitchMode::Alongside,
+            SwitchMode::Alongside => SwitchMode::AlongsideKeepDm,
+            SwitchMode::AlongsideKeepDm => SwitchMode::Replace,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum AppStep {
+    SelectSource,
     SelectDE,
+    PackageDiff,
     InputPath
 }
 
 pub struct App {
+    pub runner: Box<dyn CommandRunner>,
+    pub eos_available: bool,
     pub current_de_raw: String,
     pub current_de_profile: String,
+    pub detected_de_profile: String,
+    pub de_evidence: Vec<DeEvidence>,
     pub available_des: Vec<String>,
+    pub profiles: ProfileConfig,
+    pub local_db: LocalDb,
+    pub sync_db: SyncDb,
+    pub profile_packages: HashMap<String, Vec<String>>,
+    pub removal_packages: Vec<String>,
+    pub kept_packages: Vec<String>,
+    pub new_packages: Vec<String>,
+    pub install_packages: Vec<String>,
+    /// Display manager and greeter packages not already in `install_packages`.
+    pub dm_packages: Vec<String>,
+    /// Packages of the current profile that stay installed: shared with the target
+    /// or still needed by a kept package.
+    pub shared_packages: Vec<String>,
+    pub current_dm: Option<String>,
+    /// Index into [`display_managers::DISPLAY_MANAGERS`] overriding the target profile's
+    /// display manager, or `None` to use the profile's.
+    pub selected_dm_index: Option<usize>,
+    pub logind_available: bool,
+    pub snapshot_tool: Option<SnapshotTool>,
+    pub snapshot_enabled: bool,
+    pub write_rollback: bool,
+    pub switch_mode: SwitchMode,
+    /// Uninstall the selected profile instead of switching to it.
+    pub cleanup_mode: bool,
+    /// Other profiles that are installed, whose packages the cleanup leaves alone.
+    pub other_installed_profiles: Vec<String>,
     pub selected_de_index: usize,
     pub selected_pkg_manager_index: usize,
     pub should_quit: bool,
     pub current_step: AppStep,
+    pub source_selection_index: usize,
     pub input_buffer: String,
     pub input_cursor_position: usize,
-    pub input_error: Option<String>
+    pub input_error: Option<String>,
+    pub preview_focused: bool,
+    pub preview_scroll: usize,
+    pub preview_cursor: usize,
+    pub preview_height: usize,
+    pub diff_column: usize,
+    pub diff_selection: [usize; 3]
 }
 
 impl App {
     pub fn new() -> Result<Self> {
-        let current_de_raw = env::var("XDG_CURRENT_DESKTOP")
-            .unwrap_or_else(|_| "Unknown".to_string())
-            .split(':').last().unwrap_or("Unknown").to_string();
+        App::with_environment(Box::new(SystemRunner), &HostProbe, ProfileConfig::load()?, LocalDb::load(), SyncDb::load())
+    }
+
+    /// Builds the app on top of the given command runner, system probe, profile config
+    /// and package databases instead of the real system ones.
+    pub fn with_environment(
+        runner: Box<dyn CommandRunner>,
+        probe: &dyn SystemProbe,
+        profiles: ProfileConfig,
+        local_db: LocalDb,
+        sync_db: SyncDb,
+    ) -> Result<Self> {
+        // Without eos-packagelist (e.g. on vanilla Arch or Manjaro) fall back to the
+        // built-in and config-defined profiles and package lists.
+        let (available_des, eos_available) = match get_available_des(runner.as_ref(), &profiles) {
+            Ok(des) => (des, true),
+            Err(_) => (profiles.entries().iter().map(|e| e.profile.clone()).collect(), false),
+        };
 
-        let current_de_profile = map_raw_de_to_profile(&current_de_raw);
-        let available_des = get_available_des()?;
+        let de_evidence = system::collect_de_evidence(probe, &local_db, |raw| map_raw_de_to_profile(raw, &profiles));
+        let (current_de_raw, current_de_profile) = match system::pick_de_profile(&de_evidence) {
+            Some(e) => (e.value.clone(), e.profile.clone()),
+            None => ("Unknown".to_string(), system::UNKNOWN_PROFILE.to_string()),
+        };
+        let snapshot_tool = system::detect_snapshot_tool(probe, &local_db);
+        let detection_failed = current_de_profile == system::UNKNOWN_PROFILE;
         let initial_path = format!("./{}", App::generate_initial_filename(&current_de_profile, &available_des[0]));
         
-        Ok(App {
+        let mut app = App {
+            runner,
+            eos_available,
             current_de_raw,
+            detected_de_profile: current_de_profile.clone(),
             current_de_profile,
+            de_evidence,
             available_des,
+            profiles,
+            local_db,
+            sync_db,
+            current_dm: probe.current_display_manager(),
+            selected_dm_index: None,
+            logind_available: probe.has_logind(),
+            snapshot_tool,
+            snapshot_enabled: snapshot_tool.is_some(),
+            write_rollback: true,
+            switch_mode: SwitchMode::Replace,
+            cleanup_mode: false,
+            other_installed_profiles: Vec::new(),
+            profile_packages: HashMap::new(),
+            removal_packages: Vec::new(),
+            kept_packages: Vec::new(),
+            new_packages: Vec::new(),
+            install_packages: Vec::new(),
+            dm_packages: Vec::new(),
+            shared_packages: Vec::new(),
             selected_de_index: 0,
             selected_pkg_manager_index: 0, 
             should_quit: false,
-            current_step: AppStep::SelectDE,
+            current_step: if detection_failed { AppStep::SelectSource } else { AppStep::SelectDE },
+            source_selection_index: 0,
             input_buffer: initial_path.clone(),
             input_cursor_position: initial_path.len(),
-            input_error: None
-        })
+            input_error: None,
+            preview_focused: false,
+            preview_scroll: 0,
+            preview_cursor: 0,
+            preview_height: 0,
+            diff_column: 0,
+            diff_selection: [0; 3]
+        };
+
+        app.refresh_package_sets();
+        Ok(app)
     }
 
     fn generate_initial_filename(from_profile: &str, to_profile: &str) -> String {
         let from = from_profile.replace("-Desktop", "").replace("-Window-Manager", "");
         let to = to_profile.replace("-Desktop", "").replace("-Window-Manager", "");
         
-        if from == "Unknown-Desktop" {
+        if from_profile == system::INSTALL_ONLY_PROFILE {
+            format!("de_switcher_install_{}.sh", to)
+        } else if from_profile == system::UNKNOWN_PROFILE {
             format!("de_switcher_from_Unknown_to_{}.sh", to)
         } else {
             format!("de_switcher_{}_to_{}.sh", from.replace("-Desktop", ""), to)
@@ -140,10 +382,12 @@ impl App {
     }
     
     pub fn update_filename_on_de_change(&mut self) {
-        let new_filename = App::generate_initial_filename(
-            &self.current_de_profile,
-            &self.available_des[self.selected_de_index]
-        );
+        let target = &self.available_des[self.selected_de_index];
+        let new_filename = if self.cleanup_mode {
+            format!("de_switcher_remove_{}.sh", target.replace("-Desktop", "").replace("-Window-Manager", ""))
+        } else {
+            App::generate_initial_filename(&self.current_de_profile, target)
+        };
         
         if self.current_step == AppStep::SelectDE {
             self.input_buffer = format!("./{}", new_filename);
@@ -151,9 +395,217 @@ impl App {
         }
     }
 
+    /// The profile's packages from eos-packagelist, or else its configured or built-in
+    /// package list. The package group is only added to the eos-packagelist list, or
+    /// when no list is known, since it is then installed in place of the list.
+    fn packages_for_profile(&mut self, profile: &str) -> Vec<String> {
+        if let Some(packages) = self.profile_packages.get(profile) {
+            return packages.clone();
+        }
+
+        let mut packages = if self.eos_available && !WAYLAND_PROFILES.contains(&profile) {
+            get_profile_packages(self.runner.as_ref(), profile).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let from_eos = !packages.is_empty();
+        if !from_eos {
+            packages = self.profiles.packages(profile).to_vec();
+        }
+        if let Some(group) = self.profiles.package_group(profile)
+            && (from_eos || packages.is_empty())
+        {
+            packages.push(group.to_string());
+        }
+
+        self.profile_packages.insert(profile.to_string(), packages.clone());
+        packages
+    }
+
+    /// Replaces every package group the sync databases list with its members, so the
+    /// script names each package and pacman has nothing to prompt for. Names that are
+    /// packages themselves, or that no sync database knows, are kept as they are.
+    fn expand_groups(&self, packages: &[String]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        packages.iter()
+            .flat_map(|pkg| match self.sync_db.get(pkg) {
+                Some(_) => vec![pkg.clone()],
+                None => {
+                    let members = self.sync_db.group_members(pkg);
+                    if members.is_empty() { vec![pkg.clone()] } else { members }
+                }
+            })
+            .filter(|pkg| seen.insert(pkg.clone()))
+            .collect()
+    }
+
+    /// Recomputes the packages the script will install and remove. Removed are those
+    /// of the current profile that are installed and not shared with the target
+    /// profile, minus anything an explicitly installed user package still depends on.
+    /// Also records which target packages are not installed yet, for the rollback script.
+    pub fn refresh_package_sets(&mut self) {
+        if self.cleanup_mode {
+            self.refresh_cleanup_set();
+            return;
+        }
+
+        let current = self.current_de_profile.clone();
+        let target = self.available_des[self.selected_de_index].clone();
+
+        let listed = self.packages_for_profile(&target);
+        let mut target_packages = self.expand_groups(&listed);
+        self.install_packages = match self.profiles.package_group(&target) {
+            Some(group) if listed.iter().any(|p| p == group) => self.expand_groups(&[group.to_string()]),
+            _ => target_packages.clone(),
+        };
+
+        let dm_packages = self.display_manager_packages(&target);
+        self.dm_packages = dm_packages.iter()
+            .filter(|pkg| !self.install_packages.contains(pkg))
+            .cloned()
+            .collect();
+        // The display manager belongs to the target, so the removal must not touch it.
+        for pkg in dm_packages {
+            if !target_packages.contains(&pkg) {
+                target_packages.push(pkg);
+            }
+        }
+
+        self.new_packages = target_packages.iter()
+            .filter(|pkg| !self.local_db.is_installed(pkg))
+            .cloned()
+            .collect();
+
+        if current == system::UNKNOWN_PROFILE || current == system::INSTALL_ONLY_PROFILE || current == target {
+            self.removal_packages.clear();
+            self.kept_packages.clear();
+            self.shared_packages.clear();
+            return;
+        }
+
+        let current_packages = self.packages_for_profile(&current);
+        let current_packages = self.expand_groups(&current_packages);
+        let candidates = pacman::removal_set(&current_packages, &target_packages, &self.local_db);
+
+        let profile_packages: HashSet<&str> = current_packages.iter()
+            .chain(target_packages.iter())
+            .map(|p| p.as_str())
+            .collect();
+        let plan = pacman::protect_explicit(candidates, &profile_packages, &[&current, &target], &self.local_db);
+
+        let target_set: HashSet<&str> = target_packages.iter().map(|p| p.as_str()).collect();
+        let mut seen = HashSet::new();
+        self.shared_packages = current_packages.iter()
+            .filter(|pkg| !plan.remove.contains(pkg))
+            .filter(|pkg| target_set.contains(pkg.as_str()) || self.local_db.is_installed(pkg))
+            .filter(|pkg| seen.insert(pkg.as_str()))
+            .cloned()
+            .collect();
+
+        self.removal_packages = plan.remove;
+        self.kept_packages = plan.kept;
+    }
+
+    /// Cleanup mode: removes the selected profile's installed packages except those
+    /// another installed profile lists or depends on. Nothing is installed.
+    fn refresh_cleanup_set(&mut self) {
+        let profile = self.available_des[self.selected_de_index].clone();
+        self.install_packages.clear();
+        self.dm_packages.clear();
+        self.new_packages.clear();
+
+        let others: Vec<String> = self.available_des.clone().into_iter()
+            .filter(|p| *p != profile && self.profile_is_installed(p))
+            .collect();
+        let mut needed = Vec::new();
+        for other in &others {
+            let packages = self.packages_for_profile(other);
+            needed.extend(self.expand_groups(&packages));
+        }
+        self.other_installed_profiles = others;
+
+        let packages = self.packages_for_profile(&profile);
+        let packages = self.expand_groups(&packages);
+        let candidates = pacman::removal_set(&packages, &needed, &self.local_db);
+
+        // Only the removed profile's own packages count as profile packages, so the
+        // explicitly installed packages of the other profiles are protected too.
+        let profile_packages: HashSet<&str> = packages.iter().map(|p| p.as_str()).collect();
+        let plan = pacman::protect_explicit(candidates, &profile_packages, &[&profile], &self.local_db);
+
+        let mut seen = HashSet::new();
+        self.shared_packages = packages.iter()
+            .filter(|pkg| !plan.remove.contains(pkg) && self.local_db.is_installed(pkg))
+            .filter(|pkg| seen.insert(pkg.as_str()))
+            .cloned()
+            .collect();
+
+        self.removal_packages = plan.remove;
+        self.kept_packages = plan.kept;
+    }
+
+    /// Whether `profile` is installed: its marker package if it has one, otherwise
+    /// the first package of its list.
+    fn profile_is_installed(&mut self, profile: &str) -> bool {
+        match system::profile_marker(profile) {
+            Some(marker) => self.local_db.is_installed(marker),
+            None => self.packages_for_profile(profile).first()
+                .is_some_and(|pkg| self.local_db.is_installed(pkg)),
+        }
+    }
+
+    /// Packages of the target's display manager, plus its first greeter unless one
+    /// is already installed or among `install_packages`. Unknown display managers are
+    /// assumed to be packaged under their own name.
+    fn display_manager_packages(&self, target: &str) -> Vec<String> {
+        let name = self.target_display_manager_for(target);
+        let Some(dm) = display_managers::display_manager(name) else {
+            return vec![name.to_string()];
+        };
+
+        let mut packages: Vec<String> = dm.packages.iter().map(|p| p.to_string()).collect();
+        let has_greeter = dm.greeters.iter()
+            .any(|g| self.local_db.is_installed(g) || self.install_packages.iter().any(|p| p == g));
+        if let Some(greeter) = dm.greeters.first().filter(|_| !has_greeter) {
+            packages.push(greeter.to_string());
+        }
+        packages
+    }
+
+    /// The removed, installed and kept package lists shown on the diff screen.
+    /// Packages the target shares with the current profile are only listed as kept.
+    pub fn diff_columns(&self) -> [Vec<String>; 3] {
+        let installed = self.install_packages.iter()
+            .chain(self.dm_packages.iter())
+            .filter(|pkg| !self.shared_packages.contains(pkg))
+            .cloned()
+            .collect();
+        [self.switch_removal_packages().to_vec(), installed, self.shared_packages.clone()]
+    }
+
+    pub fn open_package_diff(&mut self) {
+        self.diff_column = 0;
+        self.diff_selection = [0; 3];
+        self.current_step = AppStep::PackageDiff;
+    }
+
+    pub fn move_diff_column(&mut self, delta: isize) {
+        self.diff_column = (self.diff_column as isize + delta).rem_euclid(DIFF_COLUMN_TITLES.len() as isize) as usize;
+    }
+
+    /// Moves the highlight in the focused diff column by `delta`, clamped to its length.
+    pub fn move_diff_selection(&mut self, delta: isize) {
+        let len = self.diff_columns()[self.diff_column].len();
+        let selected = &mut self.diff_selection[self.diff_column];
+        *selected = selected.saturating_add_signed(delta).min(len.saturating_sub(1));
+    }
+
     pub fn next_de(&mut self) {
         self.selected_de_index = (self.selected_de_index + 1) % self.available_des.len();
         self.update_filename_on_de_change();
+        self.refresh_package_sets();
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
     }
 
     pub fn previous_de(&mut self) {
@@ -163,38 +615,380 @@ impl App {
             self.selected_de_index = self.available_des.len() - 1;
         }
         self.update_filename_on_de_change();
+        self.refresh_package_sets();
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
     }
     
+    /// Moves the preview cursor by `delta` lines and scrolls just enough to keep it visible.
+    pub fn move_preview_cursor(&mut self, delta: isize) {
+        let total = self.generate_script().lines().count();
+        let height = self.preview_height.max(1);
+        self.preview_cursor = self.preview_cursor.saturating_add_signed(delta).min(total.saturating_sub(1));
+
+        if self.preview_cursor < self.preview_scroll {
+            self.preview_scroll = self.preview_cursor;
+        } else if self.preview_cursor >= self.preview_scroll + height {
+            self.preview_scroll = self.preview_cursor + 1 - height;
+        }
+    }
+
+    /// The package a script line lists, for lines inside a package array such as
+    /// `REMOVE_PACKAGES=(...)`.
+    fn package_on_line(&self, line: &str) -> Option<String> {
+        let name = line.strip_prefix("    ")?;
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || "@._+-".contains(c));
+        let known = self.local_db.is_installed(name)
+            || self.sync_db.get(name).is_some()
+            || self.install_packages.iter().any(|p| p == name);
+
+        (valid && known).then(|| name.to_string())
+    }
+
+    /// The package highlighted on the diff screen, or under the cursor of the focused preview.
+    pub fn highlighted_package(&self) -> Option<String> {
+        match self.current_step {
+            AppStep::PackageDiff => self.diff_columns()[self.diff_column]
+                .get(self.diff_selection[self.diff_column])
+                .cloned(),
+            AppStep::SelectDE if self.preview_focused => self.generate_script()
+                .lines()
+                .nth(self.preview_cursor)
+                .and_then(|line| self.package_on_line(line)),
+            _ => None,
+        }
+    }
+
+    pub fn space_estimate(&self) -> SpaceEstimate {
+        let install: Vec<String> = self.install_packages.iter().chain(self.dm_packages.iter()).cloned().collect();
+        pacman::space_estimate(&install, self.switch_removal_packages(), &self.local_db, &self.sync_db)
+    }
+
+    pub fn highlighted_package_info(&self) -> Option<PackageInfo> {
+        self.highlighted_package()
+            .and_then(|name| pacman::package_info(&name, &self.local_db, &self.sync_db))
+    }
+
+    /// Choices offered when picking the current profile: every available profile
+    /// plus install-only mode.
+    pub fn source_choices(&self) -> Vec<String> {
+        let mut choices = self.available_des.clone();
+        choices.push(system::INSTALL_ONLY_PROFILE.to_string());
+        choices
+    }
+
+    pub fn open_source_picker(&mut self) {
+        self.source_selection_index = self.source_choices().iter()
+            .position(|p| *p == self.current_de_profile)
+            .unwrap_or(0);
+        self.current_step = AppStep::SelectSource;
+    }
+
+    pub fn move_source_selection(&mut self, delta: isize) {
+        let len = self.source_choices().len() as isize;
+        self.source_selection_index = (self.source_selection_index as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Sets the "from" profile, overriding detection. `None` (install only) skips removal.
+    pub fn set_source_profile(&mut self, profile: &str) {
+        self.current_de_profile = profile.to_string();
+        self.current_step = AppStep::SelectDE;
+        self.update_filename_on_de_change();
+        self.refresh_package_sets();
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
+    }
+
+    /// True when neither eos-packagelist, a package group nor a package list knows
+    /// any packages for the target, so the script has nothing to install.
+    pub fn missing_install_list(&self) -> bool {
+        !self.cleanup_mode && self.install_packages.is_empty()
+    }
+
+    pub fn cycle_switch_mode(&mut self) {
+        self.switch_mode = self.switch_mode.next();
+    }
+
+    /// Switches between picking a target to switch to and a profile to uninstall.
+    pub fn toggle_cleanup_mode(&mut self) {
+        self.cleanup_mode = !self.cleanup_mode;
+        self.update_filename_on_de_change();
+        self.refresh_package_sets();
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
+    }
+
+    /// True when the switch script leaves the enabled display manager alone, for the
+    /// finalize script to switch later.
+    pub fn keeps_current_dm(&self) -> bool {
+        self.switch_mode == SwitchMode::AlongsideKeepDm && self.current_dm.is_some() && self.writes_finalize()
+    }
+
+    /// Packages the generated script itself removes: none when the old DE is kept.
+    pub fn switch_removal_packages(&self) -> &[String] {
+        if self.cleanup_mode {
+            return &self.removal_packages;
+        }
+        match self.switch_mode {
+            SwitchMode::Replace => &self.removal_packages,
+            SwitchMode::Alongside | SwitchMode::AlongsideKeepDm => &[],
+        }
+    }
+
+    /// Whether a rollback script is written next to the script. Cleanup has none.
+    pub fn writes_rollback(&self) -> bool {
+        self.write_rollback && !self.cleanup_mode
+    }
+
+    /// Whether a finalize script is written, i.e. the switch keeps a known old DE.
+    /// Install only and an undetected DE leave nothing to finalize.
+    pub fn writes_finalize(&self) -> bool {
+        self.switch_mode != SwitchMode::Replace
+            && !self.cleanup_mode
+            && self.current_de_profile != system::INSTALL_ONLY_PROFILE
+            && self.current_de_profile != system::UNKNOWN_PROFILE
+    }
+
+    pub fn toggle_rollback(&mut self) {
+        self.write_rollback = !self.write_rollback;
+    }
+
+    pub fn toggle_snapshot(&mut self) {
+        self.snapshot_enabled = self.snapshot_tool.is_some() && !self.snapshot_enabled;
+    }
+
     pub fn cycle_pkg_manager(&mut self) {
         self.selected_pkg_manager_index = (self.selected_pkg_manager_index + 1) % PKG_MANAGER_LIST.len();
     }
 
+    /// Steps through the profile's own display manager and then every known one.
+    pub fn cycle_display_manager(&mut self) {
+        self.selected_dm_index = match self.selected_dm_index {
+            None => Some(0),
+            Some(i) if i + 1 < display_managers::DISPLAY_MANAGERS.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        self.refresh_package_sets();
+    }
+
+    fn target_display_manager_for<'a>(&'a self, target: &'a str) -> &'a str {
+        match self.selected_dm_index {
+            Some(i) => display_managers::DISPLAY_MANAGERS[i].name,
+            None => self.profiles.display_manager(target),
+        }
+    }
+
+    /// The display manager the script enables: the user's choice or the target profile's.
+    pub fn target_display_manager(&self) -> &str {
+        self.target_display_manager_for(&self.available_des[self.selected_de_index])
+    }
+
+    /// Problems with the chosen display manager that would leave the system without a
+    /// working login screen.
+    pub fn display_manager_warnings(&self) -> Vec<String> {
+        if self.cleanup_mode {
+            return Vec::new();
+        }
+        let target = &self.available_des[self.selected_de_index];
+        let name = self.target_display_manager();
+        let Some(dm) = display_managers::display_manager(name) else {
+            return vec![format!("{} is not a known display manager: it is installed as a package of that name and {}.service is enabled.", name, name)];
+        };
+
+        let mut warnings = Vec::new();
+        if dm.needs_logind && !self.logind_available {
+            warnings.push(format!("{} needs systemd-logind, which was not found.", name));
+        }
+
+        let available = |pkg: &str| {
+            self.local_db.is_installed(pkg)
+                || self.install_packages.iter().chain(self.dm_packages.iter()).any(|p| p == pkg)
+                || self.profile_packages.get(target).is_some_and(|pkgs| pkgs.iter().any(|p| p == pkg))
+        };
+        for pkg in dm.requires.iter().filter(|pkg| !available(pkg)) {
+            warnings.push(format!("{} needs {}, which is neither installed nor part of {}.", name, pkg, target));
+        }
+        if let Some(greeter) = dm.greeters.first()
+            && self.dm_packages.iter().any(|p| p == greeter)
+        {
+            warnings.push(format!("No {} greeter is installed or part of {}, {} will be installed.", name, target, greeter));
+        }
+
+        warnings
+    }
+
     pub fn generate_filename(&self) -> String {
         self.input_buffer.clone() 
     }
 
+    /// Removal of `profile`'s packages, shared by the switch, finalize and cleanup
+    /// scripts. `reason` says why these packages go.
+    fn removal_section(&self, heading: &str, profile: &str, reason: &str) -> String {
+        let pkg_manager = PKG_MANAGER_LIST[self.selected_pkg_manager_index];
+        let sudo_cmd = if pkg_manager == "pacman" { "sudo " } else { "" };
+        let skip_reason = match profile {
+            system::INSTALL_ONLY_PROFILE => "install only, the current DE is kept".to_string(),
+            system::UNKNOWN_PROFILE => "the current DE profile is unknown, re-run de-switcher with --from <PROFILE>".to_string(),
+            profile => format!("no installed packages unique to {}", profile),
+        };
+        let kept_comment = if self.kept_packages.is_empty() {
+            " none".to_string()
+        } else {
+            self.kept_packages.iter().map(|p| format!("\n#   {}", p)).collect()
+        };
+
+        format!(
+            r#"{heading}
+# {reason}
+# CAUTION: This operation removes package dependencies recursively.
+#
+# Kept, explicitly installed packages that depend on the old DE:{kept_comment}
+
+REMOVE_PACKAGES=({removal_array})
+
+if [ ${{#REMOVE_PACKAGES[@]}} -gt 0 ]; then
+    echo "Removing ${{#REMOVE_PACKAGES[@]}} old DE packages (may prompt for password)..."
+    # -Rcs: Remove, cascade, remove dependencies only required by package(s) being removed
+    {sudo_cmd}{pkg_manager} -Rcs "${{REMOVE_PACKAGES[@]}}"
+else
+    echo "Skipping old DE removal ({skip_reason})."
+fi
+"#,
+            removal_array = script::bash_array(&self.removal_packages),
+        )
+    }
+
+    /// Disables the current display manager. Emitted before any removal: once its
+    /// package is gone the unit cannot be disabled and the `display-manager.service`
+    /// alias would be left dangling, so enabling the new one would fail.
+    fn disable_dm_section(&self) -> String {
+        let target_dm = self.target_display_manager();
+        match self.current_dm.as_deref() {
+            Some(dm) if dm == target_dm => format!("# {} is already the enabled display manager.\n\n", dm),
+            Some(dm) => format!(
+                "# DISABLE THE CURRENT DISPLAY MANAGER (before its package can be removed)\n\
+                 sudo systemctl disable --force {}\n\n",
+                dm
+            ),
+            None => "# No display manager was enabled when this script was generated.\n\n".to_string(),
+        }
+    }
+
+    /// Installs, sets up and enables the target display manager, shared by the switch
+    /// and finalize scripts. The old one is disabled by [`Self::disable_dm_section`].
+    fn display_manager_section(&self, heading: &str) -> String {
+        let pkg_manager = PKG_MANAGER_LIST[self.selected_pkg_manager_index];
+        let sudo_cmd = if pkg_manager == "pacman" { "sudo " } else { "" };
+        let target_dm = self.target_display_manager();
+
+        format!(
+            r#"{heading}
+DM_PACKAGES=({dm_array})
+
+if [ ${{#DM_PACKAGES[@]}} -gt 0 ]; then
+    echo "Installing Display Manager packages for {target_dm}..."
+    {sudo_cmd}{pkg_manager} -S --needed "${{DM_PACKAGES[@]}}"
+fi
+{setup}
+echo "Enabling Display Manager: {target_dm}"
+
+# Enable the new display manager
+sudo systemctl enable {unit}
+"#,
+            dm_array = script::bash_array(&self.dm_packages),
+            setup = display_managers::display_manager(target_dm).map_or("", |dm| dm.setup),
+            unit = display_managers::unit_for(target_dm),
+        )
+    }
+
+    fn switch_removal_reason(&self) -> String {
+        format!("Packages of the current profile ({}) that are installed and not needed by the target.", self.current_de_profile)
+    }
+
     pub fn generate_script(&self) -> String {
+        if self.cleanup_mode {
+            return self.generate_cleanup_script();
+        }
 
         let current_de_profile_for_removal = &self.current_de_profile;
         let target_de_profile = &self.available_des[self.selected_de_index];
         let pkg_manager = PKG_MANAGER_LIST[self.selected_pkg_manager_index];
         let script_file_placeholder = "de_switch_script.sh";
         let sudo_cmd = if pkg_manager == "pacman" { "sudo" } else { "" };
-        let sudo_remove_cmd = if pkg_manager == "pacman" { "sudo" } else { "" };
-
-        let target_dm = DE_DM_MAP.iter()
-            .find(|(profile, _dm)| profile == target_de_profile)
-            .map(|(_profile, dm)| *dm)
-            .unwrap_or("lightdm");
 
         let sudo_space = if sudo_cmd.is_empty() { "" } else { " " };
-        let special_install_cmd = if let Some(pkg_group) = SPECIAL_INSTALL_MAP.get(target_de_profile.as_str()) {
-            format!("echo \"Installing special package group: {}\"\n{}{}{} -S {}\n", pkg_group, sudo_cmd, sudo_space, pkg_manager, pkg_group)
-        } else {
-            format!("echo \"Installing packages for {} using eos-packagelist...\"\n{}{}{} -S $(eos-packagelist --install \"{}\")\n", target_de_profile, sudo_cmd, sudo_space, pkg_manager, target_de_profile)
+        let install_array = script::bash_array(&self.install_packages);
+        let unexpanded_note = match self.profiles.package_group(target_de_profile) {
+            _ if self.sync_db.is_empty() => format!(
+                "\n# The sync databases were not readable, so package groups are passed to {} unexpanded.",
+                pkg_manager
+            ),
+            Some(group) if self.install_packages == [group] && self.sync_db.get(group).is_none() => format!(
+                "\n# {} is not in the sync databases, so it is passed to {} unexpanded (likely a package group).",
+                group, pkg_manager
+            ),
+            _ => String::new(),
+        };
+        let session_check = match self.profiles.session(target_de_profile) {
+            Some(session) => format!(
+                "\nif [ ! -e /usr/share/wayland-sessions/{0}.desktop ] && [ ! -e /usr/share/xsessions/{0}.desktop ]; then\n    \
+                 echo \"Warning: no {0}.desktop session file was installed, the display manager may not offer {1}.\" >&2\n\
+                 fi\n",
+                session, target_de_profile
+            ),
+            None => String::new(),
         };
         
-        let sudo_remove_cmd_with_space = if sudo_remove_cmd.is_empty() { "" } else { " " };
+        let snapshot_section = match self.snapshot_tool {
+            Some(tool) if self.snapshot_enabled => script::snapshot_section(
+                tool,
+                &format!("before switching from {} to {}", current_de_profile_for_removal, target_de_profile),
+            ),
+            _ => String::new(),
+        };
+        let finalize_name = Path::new(&self.finalize_filename()).file_name()
+            .map_or_else(|| self.finalize_filename(), |n| n.to_string_lossy().to_string());
+        let removal_section = if !self.writes_finalize() {
+            self.removal_section("# 1. REMOVE CURRENT DE PACKAGES", current_de_profile_for_removal, &self.switch_removal_reason())
+        } else {
+            format!(
+                "# 1. KEEP THE CURRENT DE\n\
+                 # {0} stays installed next to {1}. Run {2} later to remove it.\n\
+                 echo \"Keeping {0} installed alongside {1}.\"\n",
+                current_de_profile_for_removal, target_de_profile, finalize_name
+            )
+        };
+        let disable_dm_section = if self.keeps_current_dm() { String::new() } else { self.disable_dm_section() };
+        let dm_section = match self.current_dm.as_deref() {
+            Some(dm) if self.keeps_current_dm() => format!(
+                "# 3. KEEP THE CURRENT DISPLAY MANAGER\n\
+                 # {0} stays enabled and lists both sessions at login. {1} switches to {2}.\n\
+                 echo \"Keeping Display Manager: {0}\"\n",
+                dm, finalize_name, self.target_display_manager()
+            ),
+            _ => self.display_manager_section("# 3. INSTALL AND ENABLE THE DISPLAY MANAGER"),
+        };
+        let space = self.space_estimate();
+        let space_comment = if self.sync_db.is_empty() {
+            "# Disk impact: unknown, the sync databases were not readable.".to_string()
+        } else {
+            format!(
+                "# Disk impact (estimated): download {}, installed {}, freed {}",
+                format_size(space.download), format_size(space.installed), format_size(space.freed)
+            )
+        };
+        let mut preflight_section = script::preflight_section(script::required_free_mb(&space));
+        if self.missing_install_list() {
+            preflight_section.push_str(&format!(
+                "\n# No package list is known for {0}: stop before anything is removed.\n\
+                 echo \"No package list is known for {0}. Install eos-packagelist or add packages to profiles.toml.\" >&2\n\
+                 exit 1\n",
+                target_de_profile
+            ));
+        }
+
+        let state_section = if self.write_rollback { script::record_state_section() } else { String::new() };
 
         format!(
             r#"#!/bin/bash
@@ -202,45 +996,25 @@ impl App {
 # Generated by Rust DE Switcher TUI
 # Target DE: {}
 # Package Manager: {}
+# Mode: {}
+{}
 #
 # REVIEW THIS SCRIPT BEFORE RUNNING:
 # bash {}
 # ----------------------------------------------------
 echo "Preparing to switch from {} to {} using {}..."
 
-# 1. REMOVE CURRENT DE PACKAGES
-# This assumes the current DE profile is one of the recognized eos-packagelist profiles.
-# CAUTION: This operation removes package dependencies recursively.
-
-CURRENT_DE_PROFILE="{}"
-
-if [ -n "$CURRENT_DE_PROFILE" ] && [ "$CURRENT_DE_PROFILE" != "Unknown-Desktop" ] && [ "$CURRENT_DE_PROFILE" != "{}" ]; then
-    echo "Creating package list for removal: $CURRENT_DE_PROFILE..."
-
-    # eos-packagelist runs as user
-    eos-packagelist "$CURRENT_DE_PROFILE" > /tmp/old_de_packages.txt
-    
-    echo "Removing old DE packages (may prompt for password)..."
-    # -Rcs: Remove, cascade, remove dependencies only required by package(s) being removed
-    {}{}{} -Rcs - < /tmp/old_de_packages.txt
-    rm /tmp/old_de_packages.txt
-
-else
-    echo "Skipping old DE removal (Current DE profile: $CURRENT_DE_PROFILE is Unknown or matches target)."
-fi
-
-# 2. INSTALL NEW DE PACKAGES
 {}
+{}{}
+{}{}
+# 2. INSTALL NEW DE PACKAGES
+# Resolved when this script was generated, so this is exactly what gets installed.{}
+INSTALL_PACKAGES=({})
 
-# 3. ENABLE THE APPROPRIATE DISPLAY MANAGER
-echo "Enabling Display Manager: {}"
-
-# Disable any currently enabled display-manager service
-sudo systemctl disable --force $(systemctl list-units --type=service --state=enabled --no-pager | grep "display-manager" | awk '{{print $1}}') 2>/dev/null
-
-# Enable the new display manager
-sudo systemctl enable {}
-
+echo "Installing ${{#INSTALL_PACKAGES[@]}} packages for {}..."
+{}{}{} -S --needed "${{INSTALL_PACKAGES[@]}}"
+{}
+{}
 # 4. Final message and reboot
 echo ""
 echo "!!! Installation and configuration complete. !!!"
@@ -259,54 +1033,308 @@ esac
 "#,
             target_de_profile,
             pkg_manager,
+            self.switch_mode.label(),
+            space_comment,
             script_file_placeholder, 
             current_de_profile_for_removal, 
             target_de_profile, 
             pkg_manager,
-            current_de_profile_for_removal, 
-            target_de_profile, 
-            sudo_remove_cmd, 
-            sudo_remove_cmd_with_space, 
+            preflight_section,
+            snapshot_section,
+            state_section,
+            disable_dm_section,
+            removal_section,
+            unexpanded_note,
+            install_array,
+            target_de_profile,
+            sudo_cmd,
+            sudo_space,
             pkg_manager,
-            special_install_cmd,
-            target_dm,
-            target_dm
+            session_check,
+            dm_section
         )
 
     }
-    
-    pub fn validate_and_finalize_path(&mut self) -> bool {
-        let p = Path::new(&self.input_buffer);
-        
-        if p.is_dir() {
-            self.input_error = Some("Path cannot be a directory. Please provide a filename.".to_string());
-            return false;
-        }
-
-        let parent = p.parent().unwrap_or(Path::new(""));
 
-        if parent.to_string_lossy() != "" && !parent.exists() {
-             self.input_error = Some("Directory does not exist.".to_string());
-             return false;
-        }
+    /// Companion script for [`SwitchMode`]s that keep the old DE: removes it and, if it
+    /// was kept, switches away from the old display manager.
+    pub fn generate_finalize_script(&self) -> String {
+        let target_de_profile = &self.available_des[self.selected_de_index];
+        let dm_section = if self.keeps_current_dm() {
+            // Switch first, so the old display manager is not removed while still enabled.
+            format!("{}{}", self.disable_dm_section(), self.display_manager_section("# 1. SWITCH THE DISPLAY MANAGER"))
+        } else {
+            String::new()
+        };
 
-        if p.file_name().is_none() || p.file_name().unwrap().to_string_lossy().is_empty() {
-             self.input_error = Some("Filename cannot be empty.".to_string());
-             return false;
-        }
+        format!(
+            r#"#!/bin/bash
+# ----------------------------------------------------
+# Generated by Rust DE Switcher TUI
+# Finalizes: de_switch_script.sh
+# Removes: {current} (kept while trying {target})
+#
+# REVIEW THIS SCRIPT BEFORE RUNNING.
+# ----------------------------------------------------
+echo "Removing {current} now that {target} is installed..."
 
-        self.input_error = None;
-        self.should_quit = true;
-        true
+{preflight}
+{dm_section}
+{removal}
+echo ""
+echo "!!! {current} has been removed. Reboot to finish. !!!"
+"#,
+            current = self.current_de_profile,
+            target = target_de_profile,
+            preflight = script::preflight_section(script::PREFLIGHT_MIN_FREE_MB),
+            removal = self.removal_section(
+                &format!("# 2. REMOVE {} PACKAGES", self.current_de_profile),
+                &self.current_de_profile,
+                &self.switch_removal_reason(),
+            ),
+        )
     }
-}
+    
+    /// Standalone script for cleanup mode: removes the selected profile and then any
+    /// orphaned dependencies, without installing anything.
+    pub fn generate_cleanup_script(&self) -> String {
+        let profile = &self.available_des[self.selected_de_index];
+        let pkg_manager = PKG_MANAGER_LIST[self.selected_pkg_manager_index];
+        let sudo_cmd = if pkg_manager == "pacman" { "sudo " } else { "" };
+        let others = if self.other_installed_profiles.is_empty() {
+            "none".to_string()
+        } else {
+            self.other_installed_profiles.join(", ")
+        };
 
-fn render_path_input(f: &mut Frame, _area: Rect, app: &mut App) {
-    let area = f.area();
-    let style = Style::default().fg(Color::White).bg(Color::Black);
-    let error_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        let snapshot_section = match self.snapshot_tool {
+            Some(tool) if self.snapshot_enabled => script::snapshot_section(tool, &format!("before removing {}", profile)),
+            _ => String::new(),
+        };
+        let mut warnings = String::new();
+        if *profile == self.current_de_profile {
+            warnings.push_str(&format!(
+                "\n# {0} is the current DE: log in to another one before running this.\n\
+                 echo \"Warning: {0} is the current DE, make sure another one is installed before rebooting.\" >&2\n",
+                profile
+            ));
+        }
+        if let Some(dm) = self.current_dm.as_deref()
+            && self.removal_packages.iter().any(|p| p == dm)
+        {
+            warnings.push_str(&format!(
+                "\n# {0} is the enabled display manager and part of {1}.\n\
+                 echo \"Warning: {0} was removed, enable another display manager before rebooting.\" >&2\n",
+                dm, profile
+            ));
+        }
 
-    let chunks = Layout::default()
+        format!(
+            r#"#!/bin/bash
+# ----------------------------------------------------
+# Generated by Rust DE Switcher TUI
+# Removes: {profile}
+# Package Manager: {pkg_manager}
+# Still installed: {others}
+# Disk impact (estimated): freed {freed}
+#
+# REVIEW THIS SCRIPT BEFORE RUNNING:
+# bash de_switch_script.sh
+# ----------------------------------------------------
+echo "Preparing to remove {profile} using {pkg_manager}..."
+
+{preflight}
+{snapshot_section}
+{removal}{warnings}
+# 2. REMOVE ORPHANED DEPENDENCIES
+mapfile -t ORPHANS < <(pacman -Qtdq)
+
+if [ ${{#ORPHANS[@]}} -gt 0 ]; then
+    echo "Removing ${{#ORPHANS[@]}} orphaned packages..."
+    {sudo_cmd}{pkg_manager} -Rns "${{ORPHANS[@]}}"
+else
+    echo "No orphaned packages left."
+fi
+
+echo ""
+echo "!!! {profile} has been removed. Reboot to finish. !!!"
+"#,
+            freed = format_size(self.space_estimate().freed),
+            preflight = script::preflight_section(script::PREFLIGHT_MIN_FREE_MB),
+            removal = self.removal_section(
+                &format!("# 1. REMOVE {} PACKAGES", profile),
+                profile,
+                &format!("Packages of {} that are installed and not needed by any other installed profile.", profile),
+            ),
+        )
+    }
+
+    /// Path of the companion rollback script: `foo.sh` becomes `foo_rollback.sh`.
+    pub fn rollback_filename(&self) -> String {
+        let full_path = self.generate_filename();
+        match full_path.strip_suffix(".sh") {
+            Some(stem) => format!("{}_rollback.sh", stem),
+            None => format!("{}_rollback.sh", full_path),
+        }
+    }
+
+    /// Path of the companion finalize script: `foo.sh` becomes `foo_finalize.sh`.
+    pub fn finalize_filename(&self) -> String {
+        let full_path = self.generate_filename();
+        match full_path.strip_suffix(".sh") {
+            Some(stem) => format!("{}_finalize.sh", stem),
+            None => format!("{}_finalize.sh", full_path),
+        }
+    }
+
+    pub fn write_script(&self) -> io::Result<()> {
+        let full_path = self.generate_filename();
+        let file_name_only = Path::new(&full_path).file_name()
+                                .map(|s| s.to_string_lossy().to_string())
+                                .unwrap_or_else(|| "de_switcher.sh".to_string());
+
+        let script_content = self.generate_script();
+        let final_script_content = script_content.replace("de_switch_script.sh", &file_name_only);
+
+        fs::write(&full_path, final_script_content)?;
+
+        if self.writes_rollback() {
+            let rollback_content = self.generate_rollback_script()
+                .replace("de_switch_script.sh", &file_name_only);
+            fs::write(self.rollback_filename(), rollback_content)?;
+        }
+
+        if self.writes_finalize() {
+            let finalize_content = self.generate_finalize_script()
+                .replace("de_switch_script.sh", &file_name_only);
+            fs::write(self.finalize_filename(), finalize_content)?;
+        }
+
+        Ok(())
+    }
+
+    /// Script undoing the switch: removes the packages the target profile added, reinstalls
+    /// what was removed and re-enables the previous display manager. It prefers the state
+    /// recorded by the switch script at run time over the lists resolved here.
+    pub fn generate_rollback_script(&self) -> String {
+        let current_de_profile = &self.current_de_profile;
+        let target_de_profile = &self.available_des[self.selected_de_index];
+        let pkg_manager = PKG_MANAGER_LIST[self.selected_pkg_manager_index];
+        let sudo_cmd = if pkg_manager == "pacman" { "sudo " } else { "" };
+        let target_dm = self.target_display_manager();
+
+        format!(
+            r#"#!/bin/bash
+# ----------------------------------------------------
+# Generated by Rust DE Switcher TUI
+# Rollback for: de_switch_script.sh
+# Restores: {current_de_profile} (undoes the switch to {target_de_profile})
+# Package Manager: {pkg_manager}
+#
+# REVIEW THIS SCRIPT BEFORE RUNNING.
+# ----------------------------------------------------
+echo "Rolling back the switch from {current_de_profile} to {target_de_profile} using {pkg_manager}..."
+
+STATE_DIR="$(cd "$(dirname "$0")" && pwd)/de_switch_script.sh.state"
+
+# Packages the switch script removed, as resolved when it was generated.
+REMOVED_PACKAGES=({removed})
+
+# Packages of {target_de_profile} that were not installed before the switch.
+NEW_PACKAGES=({new_packages})
+
+PREVIOUS_DM="{previous_dm}"
+if [ -s "$STATE_DIR/display-manager.txt" ]; then
+    PREVIOUS_DM="$(cat "$STATE_DIR/display-manager.txt")"
+fi
+
+# Disable the new display manager before step 1 may remove its package, which
+# would leave the display-manager.service alias dangling.
+if [ -n "$PREVIOUS_DM" ]; then
+    sudo systemctl disable --force {target_unit} 2>/dev/null
+fi
+
+# 1. REMOVE THE NEWLY INSTALLED PROFILE
+if [ -f "$STATE_DIR/installed-packages.txt" ]; then
+    # Only remove the packages above that were not installed before the switch. Their
+    # dependencies go with them (-s), anything else installed since is left alone.
+    mapfile -t NEW_PACKAGES < <(comm -12 <(printf '%s\n' "${{NEW_PACKAGES[@]}}" | sort) <(comm -13 "$STATE_DIR/installed-packages.txt" <(pacman -Qq | sort)))
+fi
+
+if [ ${{#NEW_PACKAGES[@]}} -gt 0 ]; then
+    echo "Removing ${{#NEW_PACKAGES[@]}} packages added by the switch..."
+    {sudo_cmd}{pkg_manager} -Rns "${{NEW_PACKAGES[@]}}"
+fi
+
+# 2. REINSTALL THE REMOVED PACKAGES
+REMOVED_DEPENDENCIES=()
+if [ -f "$STATE_DIR/installed-packages.txt" ] && [ -f "$STATE_DIR/explicit-packages.txt" ]; then
+    # Everything installed before the switch that is now missing, split by install
+    # reason, so the dependencies -Rcs cascaded into are restored as dependencies.
+    mapfile -t REMOVED_PACKAGES < <(comm -23 "$STATE_DIR/installed-packages.txt" <(pacman -Qq | sort) | comm -12 - "$STATE_DIR/explicit-packages.txt")
+    mapfile -t REMOVED_DEPENDENCIES < <(comm -23 "$STATE_DIR/installed-packages.txt" <(pacman -Qq | sort) | comm -23 - "$STATE_DIR/explicit-packages.txt")
+fi
+
+if [ ${{#REMOVED_PACKAGES[@]}} -gt 0 ]; then
+    echo "Reinstalling ${{#REMOVED_PACKAGES[@]}} removed packages..."
+    {sudo_cmd}{pkg_manager} -S --needed "${{REMOVED_PACKAGES[@]}}"
+fi
+
+if [ ${{#REMOVED_DEPENDENCIES[@]}} -gt 0 ]; then
+    echo "Reinstalling ${{#REMOVED_DEPENDENCIES[@]}} removed dependencies..."
+    {sudo_cmd}{pkg_manager} -S --needed --asdeps "${{REMOVED_DEPENDENCIES[@]}}"
+fi
+
+# 3. RESTORE THE PREVIOUS DISPLAY MANAGER
+if [ -n "$PREVIOUS_DM" ]; then
+    echo "Restoring Display Manager: $PREVIOUS_DM"
+    sudo systemctl enable --force "$PREVIOUS_DM"
+else
+    echo "No previous display manager was recorded. Enable one manually with 'sudo systemctl enable <dm>'."
+fi
+
+echo ""
+echo "!!! Rollback complete. Reboot to finish. !!!"
+"#,
+            removed = script::bash_array(self.switch_removal_packages()),
+            new_packages = script::bash_array(&self.new_packages),
+            previous_dm = self.current_dm.as_deref().unwrap_or_default(),
+            target_unit = display_managers::unit_for(target_dm),
+        )
+    }
+
+    pub fn validate_and_finalize_path(&mut self) -> bool {
+        let p = Path::new(&self.input_buffer);
+        
+        if p.is_dir() {
+            self.input_error = Some("Path cannot be a directory. Please provide a filename.".to_string());
+            return false;
+        }
+
+        let parent = p.parent().unwrap_or(Path::new(""));
+
+        if parent.to_string_lossy() != "" && !parent.exists() {
+             self.input_error = Some("Directory does not exist.".to_string());
+             return false;
+        }
+
+        if p.file_name().is_none() || p.file_name().unwrap().to_string_lossy().is_empty() {
+             self.input_error = Some("Filename cannot be empty.".to_string());
+             return false;
+        }
+
+        self.input_error = None;
+        self.should_quit = true;
+        true
+    }
+}
+
+fn render_path_input(f: &mut Frame, _area: Rect, app: &mut App) {
+    let area = f.area();
+    let style = Style::default().fg(Color::White).bg(Color::Black);
+    let error_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+
+    let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Ratio(1, 3), 
@@ -355,14 +1383,31 @@ fn render_path_input(f: &mut Frame, _area: Rect, app: &mut App) {
 }
 
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
+    let args = match cli::parse_args(env::args().skip(1)) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("error: {}\n\nRun with --help for usage.", e);
+            return Ok(ExitCode::from(cli::EXIT_USAGE));
+        }
+    };
+
+    if args.help {
+        cli::print_usage();
+        return Ok(ExitCode::SUCCESS);
+    }
+
     let mut app = match App::new() {
         Ok(a) => a,
         Err(e) => {
-            eprintln!("Error initializing app (could not run eos-packagelist): {}", e);
-            return Err(e);
+            eprintln!("Error initializing app: {}", e);
+            return Ok(ExitCode::FAILURE);
         }
     };
+
+    if args.is_non_interactive() {
+        return Ok(cli::run(args, app));
+    }
     
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen)?;
@@ -374,26 +1419,29 @@ fn main() -> Result<()> {
     execute!(terminal.backend_mut(), Show)?;
     disable_raw_mode()?;
 
-    if let Err(e) = result {
-        return Err(e);
-    }
+    result?;
     
     if app.should_quit {
         let full_path = app.generate_filename(); 
-        let file_name_only = Path::new(&full_path).file_name()
-                                .map(|s| s.to_string_lossy().to_string())
-                                .unwrap_or_else(|| "de_switcher.sh".to_string());
-        
-        let script_content = app.generate_script(); 
-        let final_script_content = script_content.replace("de_switch_script.sh", &file_name_only); 
 
-        match fs::write(&full_path, final_script_content) {
-            Ok(_) => println!("\nScript successfully written to **{}**\n\n**NEXT STEP: REVIEW AND RUN:**\n\t`chmod +x {}`\n\t`{}`\n", full_path, full_path, full_path),
-            Err(e) => eprintln!("\nError writing script file: {}", e),
+        match app.write_script() {
+            Ok(_) => {
+                println!("\nScript successfully written to **{}**\n\n**NEXT STEP: REVIEW AND RUN:**\n\t`chmod +x {}`\n\t`{}`\n", full_path, full_path, full_path);
+                if app.writes_rollback() {
+                    println!("Rollback script written to **{}**\n", app.rollback_filename());
+                }
+                if app.writes_finalize() {
+                    println!("Finalize script written to **{}**, run it to remove {} later.\n", app.finalize_filename(), app.current_de_profile);
+                }
+            }
+            Err(e) => {
+                eprintln!("\nError writing script file: {}", e);
+                return Ok(ExitCode::FAILURE);
+            }
         }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
@@ -417,59 +1465,94 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
             }
         })?;
 
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match app.current_step {
-                    AppStep::SelectDE => match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-                        KeyCode::Char('j') | KeyCode::Down => app.next_de(),
-                        KeyCode::Char('k') | KeyCode::Up => app.previous_de(),
-                        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.cycle_pkg_manager(),
-                        KeyCode::Tab => app.cycle_pkg_manager(),
-                        KeyCode::Enter => {
-                            app.current_step = AppStep::InputPath;
-                            app.input_error = None; 
-                        }
-                        _ => {}
-                    },
-                    AppStep::InputPath => match key.code {
-                        KeyCode::Char(c) => {
-                            app.input_buffer.insert(app.input_cursor_position, c);
-                            app.input_cursor_position += 1;
-                        }
-                        KeyCode::Backspace => {
-                            if app.input_cursor_position > 0 {
-                                app.input_cursor_position -= 1;
-                                app.input_buffer.remove(app.input_cursor_position);
-                            }
-                        }
-                        KeyCode::Delete => {
-                            if app.input_cursor_position < app.input_buffer.len() {
-                                app.input_buffer.remove(app.input_cursor_position);
-                            }
-                        }
-                        KeyCode::Left => {
-                            if app.input_cursor_position > 0 {
-                                app.input_cursor_position -= 1;
-                            }
-                        }
-                        KeyCode::Right => {
-                            if app.input_cursor_position < app.input_buffer.len() {
-                                app.input_cursor_position += 1;
-                            }
-                        }
-                        KeyCode::Enter => {
-                            if app.validate_and_finalize_path() {
-                                return Ok(()); 
-                            }
-                        }
-                        KeyCode::Esc => {
-                            app.current_step = AppStep::SelectDE;
-                            app.input_error = None;
-                            app.update_filename_on_de_change(); 
-                        }
-                        _ => {}
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            let page = app.preview_height.max(1) as isize;
+
+            match app.current_step {
+                AppStep::SelectSource => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down => app.move_source_selection(1),
+                    KeyCode::Char('k') | KeyCode::Up => app.move_source_selection(-1),
+                    KeyCode::Enter => {
+                        let choice = app.source_choices()[app.source_selection_index].clone();
+                        app.set_source_profile(&choice);
                     }
+                    KeyCode::Esc => app.current_step = AppStep::SelectDE,
+                    _ => {}
+                },
+                AppStep::SelectDE if app.preview_focused => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('h') | KeyCode::Left | KeyCode::Esc => app.preview_focused = false,
+                    KeyCode::Char('j') | KeyCode::Down => app.move_preview_cursor(1),
+                    KeyCode::Char('k') | KeyCode::Up => app.move_preview_cursor(-1),
+                    KeyCode::PageDown | KeyCode::Char(' ') => app.move_preview_cursor(page),
+                    KeyCode::PageUp => app.move_preview_cursor(-page),
+                    KeyCode::Char('g') | KeyCode::Home => app.move_preview_cursor(isize::MIN),
+                    KeyCode::Char('G') | KeyCode::End => app.move_preview_cursor(isize::MAX),
+                    _ => {}
+                },
+                AppStep::SelectDE => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down => app.next_de(),
+                    KeyCode::Char('k') | KeyCode::Up => app.previous_de(),
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.cycle_pkg_manager(),
+                    KeyCode::Tab => app.cycle_pkg_manager(),
+                    KeyCode::Char('m') => app.cycle_display_manager(),
+                    KeyCode::Char('s') => app.toggle_snapshot(),
+                    KeyCode::Char('r') => app.toggle_rollback(),
+                    KeyCode::Char('o') => app.cycle_switch_mode(),
+                    KeyCode::Char('u') => app.toggle_cleanup_mode(),
+                    KeyCode::Char('f') => app.open_source_picker(),
+                    KeyCode::Char('d') => app.open_package_diff(),
+                    KeyCode::Char('l') | KeyCode::Right => app.preview_focused = true,
+                    KeyCode::Enter => {
+                        app.current_step = AppStep::InputPath;
+                        app.input_error = None; 
+                    }
+                    _ => {}
+                },
+                AppStep::PackageDiff => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('d') | KeyCode::Esc => app.current_step = AppStep::SelectDE,
+                    KeyCode::Char('h') | KeyCode::Left => app.move_diff_column(-1),
+                    KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => app.move_diff_column(1),
+                    KeyCode::Char('j') | KeyCode::Down => app.move_diff_selection(1),
+                    KeyCode::Char('k') | KeyCode::Up => app.move_diff_selection(-1),
+                    KeyCode::PageDown | KeyCode::Char(' ') => app.move_diff_selection(page),
+                    KeyCode::PageUp => app.move_diff_selection(-page),
+                    KeyCode::Char('g') | KeyCode::Home => app.diff_selection[app.diff_column] = 0,
+                    KeyCode::Char('G') | KeyCode::End => app.move_diff_selection(isize::MAX),
+                    _ => {}
+                },
+                AppStep::InputPath => match key.code {
+                    KeyCode::Char(c) => {
+                        app.input_buffer.insert(app.input_cursor_position, c);
+                        app.input_cursor_position += 1;
+                    }
+                    KeyCode::Backspace if app.input_cursor_position > 0 => {
+                        app.input_cursor_position -= 1;
+                        app.input_buffer.remove(app.input_cursor_position);
+                    }
+                    KeyCode::Delete if app.input_cursor_position < app.input_buffer.len() => {
+                        app.input_buffer.remove(app.input_cursor_position);
+                    }
+                    KeyCode::Left if app.input_cursor_position > 0 => {
+                        app.input_cursor_position -= 1;
+                    }
+                    KeyCode::Right if app.input_cursor_position < app.input_buffer.len() => {
+                        app.input_cursor_position += 1;
+                    }
+                    KeyCode::Enter if app.validate_and_finalize_path() => {
+                        return Ok(());
+                    }
+                    KeyCode::Esc => {
+                        app.current_step = AppStep::SelectDE;
+                        app.input_error = None;
+                        app.update_filename_on_de_change(); 
+                    }
+                    _ => {}
                 }
             }
         }
@@ -484,21 +1567,40 @@ fn render_ui(frame: &mut Frame, _area: Rect, app: &mut App) {
         render_path_input(frame, area, app);
         return;
     }
+    if app.current_step == AppStep::PackageDiff {
+        render_package_diff(frame, app);
+        return;
+    }
     
+    let banner_height = if app.eos_available { 0 } else { 1 };
     let vertical_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(1),     
+            Constraint::Length(banner_height),
             Constraint::Min(0),        
             Constraint::Length(1),     
         ])
         .split(area);
         
     let top_bar_area = vertical_chunks[0];
-    let main_area = vertical_chunks[1];
-    let footer_area = vertical_chunks[2];
+    let banner_area = vertical_chunks[1];
+    let main_area = vertical_chunks[2];
+    let footer_area = vertical_chunks[3];
+
+    if !app.eos_available {
+        let target = &app.available_des[app.selected_de_index];
+        let banner_text = if app.missing_install_list() {
+            format!(" ! eos-packagelist is not available and no package list is known for {}. Add one to profiles.toml. ", target)
+        } else {
+            " ! eos-packagelist is not available: using built-in and config-defined package lists. ".to_string()
+        };
+        let banner = Paragraph::new(banner_text)
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD));
+        frame.render_widget(banner, banner_area);
+    }
 
-    let header_title = format!(" de-switcher | Quickly switch desktop environments using eos-packagelist. ");
+    let header_title = " de-switcher | Quickly switch desktop environments using eos-packagelist. ";
     let header_block = Block::default()
         .title(header_title)
         .title_alignment(Alignment::Left)
@@ -544,8 +1646,9 @@ fn render_ui(frame: &mut Frame, _area: Rect, app: &mut App) {
     let info_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Ratio(1, 2), 
-            Constraint::Ratio(1, 2), 
+            Constraint::Ratio(2, 5), 
+            Constraint::Ratio(1, 5), 
+            Constraint::Ratio(2, 5), 
         ])
         .split(info_and_pkg_area);
 
@@ -555,34 +1658,141 @@ fn render_ui(frame: &mut Frame, _area: Rect, app: &mut App) {
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Blue));
     
+    let removal_summary = if app.local_db.is_empty() {
+        "unknown (pacman local DB unavailable)".to_string()
+    } else {
+        app.switch_removal_packages().len().to_string()
+    };
+
+    let profile_label = if app.current_de_profile == app.detected_de_profile {
+        app.current_de_profile.clone()
+    } else {
+        format!("{} (override, detected {})", app.current_de_profile, app.detected_de_profile)
+    };
+
+    let evidence_summary = if app.de_evidence.is_empty() {
+        "none".to_string()
+    } else {
+        app.de_evidence.iter()
+            .map(|e| format!("{}={} ({})", e.source, e.value, e.profile))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
     let info_text = format!(
-        "Current DE: **{}**\nProfile: **{}**\n\n\
-         Use **j/k** or Up/Down to select a target DE.\n\
-         Press **Ctrl+P** or **Tab** to change the Package Manager.\n\
+        "Current DE: **{}**\nProfile: **{}**\nDisplay Manager: **{}**\nPackages to remove: **{}**\nEvidence: {}\n\n\
+         Use **j/k** or Up/Down to select a target DE, **f** to pick the current profile.\n\
+         Press **Ctrl+P** or **Tab** to change the Package Manager, **m** the Display Manager, **s** to toggle the snapshot, **o** the mode, **u** to uninstall a DE instead.\n\
+         Press **l**/Right to scroll the script preview, **h**/Left to go back, **d** to review the package diff.\n\
          Press **<ENTER>** to set output path.", 
         app.current_de_raw,
-        app.current_de_profile
+        profile_label,
+        app.current_dm.as_deref().unwrap_or("none enabled"),
+        removal_summary,
+        evidence_summary
     );
 
-    let info_paragraph = Paragraph::new(info_text).block(info_block);
+    let info_paragraph = Paragraph::new(info_text)
+        .block(info_block)
+        .wrap(Wrap { trim: true });
     frame.render_widget(info_paragraph, info_chunks[0]);
     
+    let kept_block = Block::default()
+        .title(format!(" Kept (explicitly installed): {} ", app.kept_packages.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let kept_text = if app.kept_packages.is_empty() {
+        "No explicitly installed packages depend on the old DE.".to_string()
+    } else {
+        app.kept_packages.join(", ")
+    };
+
+    let kept_paragraph = Paragraph::new(kept_text)
+        .block(kept_block)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(kept_paragraph, info_chunks[1]);
+
     let current_pkg_manager = PKG_MANAGER_LIST[app.selected_pkg_manager_index];
     let pkg_manager_block = Block::default()
-        .title(" Package Manager (Ctrl+P/Tab to cycle) ")
+        .title(" Package Manager (Tab) / Display Manager (m) ")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Magenta));
 
-    let pkg_manager_text = format!(
-        "Selected: **{}**\n\n\
-         Note: {} is used for installation commands, e.g., `{} -S ...`",
-        current_pkg_manager,
-        current_pkg_manager,
-        current_pkg_manager
-    );
+    let dm_label = if app.selected_dm_index.is_some() { "chosen" } else { "profile default" };
+    let mut pkg_manager_text = vec![
+        Line::from(format!("Package Manager: **{}**, e.g. `{} -S ...`", current_pkg_manager, current_pkg_manager)),
+        Line::from(format!("Display Manager: **{}** ({})", app.target_display_manager(), dm_label)),
+    ];
+    pkg_manager_text.extend(app.display_manager_warnings().into_iter().map(|w| {
+        Line::from(Span::styled(format!("! {}", w), Style::default().fg(Color::Red)))
+    }));
     
-    let pkg_manager_paragraph = Paragraph::new(pkg_manager_text).block(pkg_manager_block);
-    frame.render_widget(pkg_manager_paragraph, info_chunks[1]);
+    let pkg_manager_paragraph = Paragraph::new(pkg_manager_text)
+        .block(pkg_manager_block)
+        .wrap(Wrap { trim: true });
+    let settings_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(info_chunks[2]);
+
+    frame.render_widget(pkg_manager_paragraph, settings_chunks[0]);
+
+    let space_block = Block::default()
+        .title(" Disk Impact ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let space = app.space_estimate();
+    let space_text = if app.sync_db.is_empty() {
+        "Sync databases unavailable, run `pacman -Syu` for an estimate.".to_string()
+    } else {
+        let mut text = format!(
+            "Download: **{}**\nInstalled: **+{}**\nFreed: **-{}**\nFree space required: **{} MiB**",
+            format_size(space.download),
+            format_size(space.installed),
+            format_size(space.freed),
+            script::required_free_mb(&space)
+        );
+        if !space.unknown.is_empty() {
+            text.push_str(&format!("\nNot in sync DBs: {}", space.unknown.join(", ")));
+        }
+        text
+    };
+
+    let space_paragraph = Paragraph::new(space_text)
+        .block(space_block)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(space_paragraph, settings_chunks[1]);
+
+    let snapshot_block = Block::default()
+        .title(" Snapshot / Rollback / Mode (s/r/o) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let mut snapshot_text = match app.snapshot_tool {
+        Some(tool) => format!(
+            "Tool: **{}**\nPre-switch snapshot: **{}**\nRollback script: **{}**",
+            tool.name(),
+            if app.snapshot_enabled { "enabled" } else { "disabled" },
+            if app.write_rollback { "enabled" } else { "disabled" }
+        ),
+        None => format!(
+            "No snapper (btrfs) or timeshift installation found.\nRollback script: **{}**",
+            if app.write_rollback { "enabled" } else { "disabled" }
+        ),
+    };
+    let mode_label = if app.cleanup_mode { "Remove a DE (cleanup)" } else { app.switch_mode.label() };
+    snapshot_text.push_str(&format!("\nMode: **{}**", mode_label));
+
+    let snapshot_paragraph = Paragraph::new(snapshot_text)
+        .block(snapshot_block)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(snapshot_paragraph, settings_chunks[2]);
 
 
     let items: Vec<ListItem> = app.available_des.iter()
@@ -592,12 +1802,13 @@ fn render_ui(frame: &mut Frame, _area: Rect, app: &mut App) {
             } else { 
                 Style::default().fg(Color::White) 
             };
-            ListItem::new(de.as_str()).style(style)
+            ListItem::new(app.profiles.display_name(de)).style(style)
         })
         .collect();
 
+    let list_title = if app.cleanup_mode { " Available DE Profiles (DE to remove, u to switch instead) " } else { " Available DE Profiles (Target DE) " };
     let list_block = Block::default()
-        .title(" Available DE Profiles (Target DE) ")
+        .title(list_title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Green));
 
@@ -615,13 +1826,915 @@ fn render_ui(frame: &mut Frame, _area: Rect, app: &mut App) {
     let selected_de_name = &app.available_des[app.selected_de_index];
     let script_content = app.generate_script();
     
+    let script_lines: Vec<&str> = script_content.lines().collect();
+    let total_lines = script_lines.len();
+
+    let preview_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(if app.preview_focused {
+            [Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)]
+        } else {
+            [Constraint::Ratio(1, 1), Constraint::Length(0)]
+        })
+        .split(chunks[1]);
+
+    app.preview_height = chunks[1].height.saturating_sub(2) as usize;
+    app.preview_scroll = app.preview_scroll.min(total_lines.saturating_sub(app.preview_height.max(1)));
+
+    let first_visible = app.preview_scroll;
+    let last_visible = (first_visible + app.preview_height).min(total_lines);
+    let percent = if total_lines <= app.preview_height { 100 } else { last_visible * 100 / total_lines };
+
+    let (border_style, border_type, title_hint) = if app.preview_focused {
+        (Style::default().fg(Color::Red).add_modifier(Modifier::BOLD), BorderType::Thick, "j/k PgUp/PgDn g/G move, h to leave")
+    } else {
+        (Style::default().fg(Color::Red), BorderType::Plain, "l to focus")
+    };
+
     let script_block = Block::default()
-        .title(format!(" Script Preview for: {} ", selected_de_name))
+        .title(format!(" Script Preview for: {} ({}) ", selected_de_name, title_hint))
+        .title_bottom(Line::from(format!(
+            " lines {}-{}/{} ({}%) ",
+            (first_visible + 1).min(total_lines), last_visible, total_lines, percent
+        )).right_aligned())
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(border_style);
+
+    let gutter_width = total_lines.to_string().len();
+    let gutter_style = Style::default().fg(Color::DarkGray);
+    let danger_gutter_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let preview_lines: Vec<Line> = script_lines[first_visible..last_visible].iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let (marker, style) = if highlight::is_danger_line(line) {
+                ('!', danger_gutter_style)
+            } else {
+                ('│', gutter_style)
+            };
+            let mut spans = vec![
+                Span::styled(format!("{:>width$} {} ", first_visible + i + 1, marker, width = gutter_width), style),
+            ];
+            spans.extend(highlight::highlight_line(line));
+            if app.preview_focused && first_visible + i == app.preview_cursor {
+                Line::from(spans).style(Style::default().bg(Color::DarkGray))
+            } else {
+                Line::from(spans)
+            }
+        })
+        .collect();
+
+    let script_paragraph = Paragraph::new(preview_lines).block(script_block);
+
+    frame.render_widget(script_paragraph, preview_chunks[0]);
+
+    if app.preview_focused {
+        render_package_info(frame, preview_chunks[1], app);
+    }
+
+    if app.current_step == AppStep::SelectSource {
+        render_source_picker(frame, app);
+    }
+}
+
+fn render_package_diff(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let target = &app.available_des[app.selected_de_index];
+
+    let outer_block = Block::default()
+        .title(if app.cleanup_mode {
+            format!(" Package diff: remove {} ", target)
+        } else {
+            format!(" Package diff: {} -> {} ", app.current_de_profile, target)
+        })
+        .title_bottom(Line::from(" h/l column, j/k PgUp/PgDn g/G move, d/ESC back, q quit ").right_aligned())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner_area = outer_block.inner(area);
+    frame.render_widget(outer_block, area);
+
+    let diff_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(8),
+        ])
+        .split(inner_area);
+
+    let column_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(diff_chunks[0]);
+
+    let colors = [Color::Red, Color::Green, Color::Yellow];
+    let installed_style = Style::default().fg(Color::DarkGray);
+
+    for (index, packages) in app.diff_columns().iter().enumerate() {
+        let focused = index == app.diff_column;
+        let (border_type, border_style) = if focused {
+            (BorderType::Thick, Style::default().fg(colors[index]).add_modifier(Modifier::BOLD))
+        } else {
+            (BorderType::Plain, Style::default().fg(colors[index]))
+        };
+
+        let items: Vec<ListItem> = packages.iter()
+            .map(|pkg| {
+                let mut spans = vec![Span::raw(pkg.as_str())];
+                if app.local_db.is_installed(pkg) {
+                    spans.push(Span::styled(" (installed)", installed_style));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default()
+                .title(format!(" {}: {} ", DIFF_COLUMN_TITLES[index], packages.len()))
+                .borders(Borders::ALL)
+                .border_type(border_type)
+                .border_style(border_style))
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan))
+            .highlight_symbol(if focused { ">> " } else { "   " });
+
+        let mut list_state = ListState::default();
+        if !packages.is_empty() {
+            list_state.select(Some(app.diff_selection[index].min(packages.len() - 1)));
+        }
+
+        frame.render_stateful_widget(list, column_areas[index], &mut list_state);
+    }
+
+    render_package_info(frame, diff_chunks[1], app);
+}
+
+/// Formats a byte count the way pacman does, e.g. `12.34 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.2} {}", size, UNITS[unit]) }
+}
+
+fn render_package_info(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .title(" Package Info ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red));
+        .border_style(Style::default().fg(Color::Blue));
+
+    let label_style = Style::default().fg(Color::DarkGray);
+    let text = match (app.highlighted_package(), app.highlighted_package_info()) {
+        (None, _) => vec![Line::from(Span::styled("Highlight a package to inspect it.", label_style))],
+        (Some(name), None) => vec![Line::from(format!("{}: not installed and not found in the sync databases.", name))],
+        (Some(_), Some(info)) => {
+            let status = match (info.installed, info.repository.as_deref()) {
+                (true, Some(repo)) => format!("installed, {}", repo),
+                (true, None) => "installed, not in any repository".to_string(),
+                (false, Some(repo)) => format!("not installed, {}", repo),
+                (false, None) => "not installed".to_string(),
+            };
+            let required_by = if info.required_by.is_empty() {
+                "nothing".to_string()
+            } else {
+                info.required_by.join(", ")
+            };
+
+            vec![
+                Line::from(vec![
+                    Span::styled(info.name, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" {} ({})", info.version, status)),
+                ]),
+                Line::from(info.description),
+                Line::from(vec![Span::styled("Installed size: ", label_style), Span::raw(format_size(info.installed_size))]),
+                Line::from(vec![Span::styled("Required by: ", label_style), Span::raw(required_by)]),
+            ]
+        }
+    };
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, area);
+}
+
+fn render_source_picker(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let choices = app.source_choices();
+
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(choices.len() as u16 + 4),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let popup_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(2, 4),
+            Constraint::Ratio(1, 4),
+        ])
+        .split(vertical[1])[1];
+
+    frame.render_widget(Clear, popup_area);
+
+    let title = if app.detected_de_profile == system::UNKNOWN_PROFILE {
+        " Current DE could not be detected: pick the profile to remove (ESC to skip) "
+    } else {
+        " Pick the current DE profile to remove (ESC to cancel) "
+    };
+
+    let items: Vec<ListItem> = choices.iter()
+        .map(|profile| {
+            let label = if profile == system::INSTALL_ONLY_PROFILE {
+                "None, install only (keep the current DE)".to_string()
+            } else if *profile == app.detected_de_profile {
+                format!("{} (detected)", app.profiles.display_name(profile))
+            } else {
+                app.profiles.display_name(profile).to_string()
+            };
+            ListItem::new(label)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan))
+        .highlight_symbol(">> ");
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(app.source_selection_index));
+
+    frame.render_stateful_widget(list, popup_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacman::testing::{local_db, sync_db, sync_db_with_groups};
+    use runner::fake::ScriptedRunner;
+    use system::fake::FakeProbe;
+
+    fn map(raw: &str) -> String {
+        map_raw_de_to_profile(raw, &ProfileConfig::builtin())
+    }
+
+    #[test]
+    fn maps_known_xdg_names() {
+        assert_eq!(map("KDE"), "KDE-Desktop");
+        assert_eq!(map("plasma"), "KDE-Desktop");
+        assert_eq!(map("X-Cinnamon"), "Cinnamon-Desktop");
+        assert_eq!(map("XFCE"), "XFCE4-Desktop");
+        assert_eq!(map("LXQt"), "LXQT-Desktop");
+        assert_eq!(map("MATE"), "MATE-Desktop");
+        assert_eq!(map("i3"), "i3-Window-Manager");
+        assert_eq!(map("COSMIC"), "COSMIC-Desktop");
+    }
 
-    let preview_text: String = script_content.lines().take(30).collect::<Vec<&str>>().join("\n");
-    let script_paragraph = Paragraph::new(preview_text).block(script_block);
+    #[test]
+    fn maps_colon_separated_lists() {
+        assert_eq!(map("ubuntu:GNOME"), "GNOME-Desktop");
+        assert_eq!(map("Budgie:GNOME"), "Budgie-Desktop");
+        assert_eq!(map("pop:GNOME"), "GNOME-Desktop");
+    }
+
+    #[test]
+    fn matching_ignores_case_and_whitespace() {
+        assert_eq!(map("kde"), "KDE-Desktop");
+        assert_eq!(map(" Gnome "), "GNOME-Desktop");
+        assert_eq!(map("xfce4-desktop"), "XFCE4-Desktop");
+    }
+
+    #[test]
+    fn ambiguous_or_unknown_names_are_unknown() {
+        assert_eq!(map("LX"), system::UNKNOWN_PROFILE);
+        assert_eq!(map("GN"), system::UNKNOWN_PROFILE);
+        assert_eq!(map(""), system::UNKNOWN_PROFILE);
+        assert_eq!(map("enlightenment"), system::UNKNOWN_PROFILE);
+    }
+
+    #[test]
+    fn maps_wayland_compositors() {
+        assert_eq!(map("Hyprland"), "Hyprland");
+        assert_eq!(map("sway"), "Sway");
+        assert_eq!(map("niri"), "niri");
+        assert_eq!(map("River"), "river");
+    }
+
+    #[test]
+    fn maps_session_names_of_configured_profiles() {
+        let profiles = ProfileConfig::with_overrides(r#"
+            [profiles.Wayfire]
+            display_manager = "greetd"
+            session = "wayfire-session"
+        "#).unwrap();
+        assert_eq!(map_raw_de_to_profile("wayfire-session", &profiles), "Wayfire");
+    }
+
+    #[test]
+    fn every_alias_is_lowercase() {
+        for (alias, _) in DE_ALIASES {
+            assert_eq!(*alias, alias.to_lowercase());
+        }
+    }
+
+    fn eos_runner() -> ScriptedRunner {
+        ScriptedRunner::new()
+            .with_output("eos-packagelist --list", "Base\nKDE-Desktop\nGNOME-Desktop\ni3-Window-Manager\nPrinting-Support\n")
+            .with_output("eos-packagelist KDE-Desktop", "plasma-meta\nkate\nsddm\nqt6-base\nplasma-integration\n")
+            .with_output("eos-packagelist GNOME-Desktop", "gnome\ngdm\nqt6-base\n")
+    }
+
+    fn app_switching_kde_to_gnome(db: LocalDb) -> App {
+        app_switching_kde_to_gnome_with(eos_runner(), db)
+    }
+
+    fn app_switching_kde_to_gnome_with(runner: ScriptedRunner, db: LocalDb) -> App {
+        app_switching_kde_to_gnome_on(FakeProbe::new().with_logind(), runner, db)
+    }
+
+    /// The KDE to GNOME switch on a system where sddm is the enabled display manager.
+    fn app_switching_kde_to_gnome_from_sddm(db: LocalDb) -> App {
+        app_switching_kde_to_gnome_on(FakeProbe::new().with_logind().with_display_manager("sddm"), eos_runner(), db)
+    }
+
+    fn app_switching_kde_to_gnome_on(probe: FakeProbe, runner: ScriptedRunner, db: LocalDb) -> App {
+        let mut app = App::with_environment(Box::new(runner), &probe, ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+        app
+    }
+
+    #[test]
+    fn system_state_is_read_through_the_probe() {
+        let probe = FakeProbe::new()
+            .with_env("XDG_CURRENT_DESKTOP", "KDE")
+            .with_root_filesystem("btrfs")
+            .with_display_manager("sddm")
+            .with_logind();
+        let db = local_db("probe", &[("snapper", true, &[])]);
+        let app = App::with_environment(Box::new(eos_runner()), &probe, ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+
+        assert_eq!(app.current_de_profile, "KDE-Desktop");
+        assert_eq!(app.current_step, AppStep::SelectDE);
+        assert_eq!(app.current_dm.as_deref(), Some("sddm"));
+        assert!(app.logind_available);
+        assert_eq!(app.snapshot_tool, Some(SnapshotTool::Snapper));
+
+        let app = App::with_environment(Box::new(eos_runner()), &FakeProbe::new(), ProfileConfig::builtin(), LocalDb::default(), SyncDb::default()).unwrap();
+        assert_eq!(app.current_de_profile, system::UNKNOWN_PROFILE);
+        assert_eq!(app.current_step, AppStep::SelectSource);
+        assert!(app.current_dm.is_none() && !app.logind_available && app.snapshot_tool.is_none());
+    }
 
-    frame.render_widget(script_paragraph, chunks[1]);
+    #[test]
+    fn discovers_profiles_from_eos_packagelist() {
+        let runner = ScriptedRunner::new().with_output("eos-packagelist --list", "Base\nKDE-Desktop\n  GNOME-Desktop  \ni3-Window-Manager\n");
+        let des = get_available_des(&runner, &ProfileConfig::builtin()).unwrap();
+        assert_eq!(des, ["KDE-Desktop", "GNOME-Desktop", "i3-Window-Manager", "Hyprland", "Sway", "niri", "river"]);
+    }
+
+    #[test]
+    fn failed_profile_listing_falls_back_to_builtin_profiles() {
+        let runner = ScriptedRunner::new().with_failure("eos-packagelist --list");
+        let des = get_available_des(&runner, &ProfileConfig::builtin()).unwrap();
+        assert_eq!(des.len(), ProfileConfig::builtin().entries().len());
+        assert!(des.contains(&"KDE-Desktop".to_string()));
+    }
+
+    #[test]
+    fn missing_eos_packagelist_is_an_error() {
+        let runner = ScriptedRunner::new();
+        let err = get_available_des(&runner, &ProfileConfig::builtin()).unwrap_err();
+        assert!(err.to_string().contains("could not run eos-packagelist"));
+    }
+
+    #[test]
+    fn removal_skips_shared_and_uninstalled_packages() {
+        let db = local_db("removal", &[
+            ("plasma-meta", true, &[]),
+            ("sddm", true, &["qt6-base"]),
+            ("qt6-base", false, &[]),
+        ]);
+        let app = app_switching_kde_to_gnome(db);
+
+        assert_eq!(app.removal_packages, ["plasma-meta", "sddm"]);
+        let script = app.generate_script();
+        assert!(script.contains("REMOVE_PACKAGES=(\n    plasma-meta\n    sddm\n)"));
+        assert!(script.contains("INSTALL_PACKAGES=(\n    gnome\n    gdm\n    qt6-base\n)"));
+    }
+
+    #[test]
+    fn script_inlines_resolved_packages_instead_of_calling_eos_packagelist() {
+        let db = local_db("inline", &[("plasma-meta", true, &[])]);
+        let script = app_switching_kde_to_gnome(db).generate_script();
+
+        assert!(!script.contains("$(eos-packagelist"));
+        assert!(script.contains("-S --needed \"${INSTALL_PACKAGES[@]}\""));
+    }
+
+    #[test]
+    fn explicit_user_packages_keep_their_dependencies() {
+        let db = local_db("kept", &[
+            ("plasma-meta", true, &["plasma-integration"]),
+            ("plasma-integration", false, &["qt6-base"]),
+            ("qt6-base", false, &[]),
+            ("firefox", true, &["plasma-integration>=6"]),
+        ]);
+        let app = app_switching_kde_to_gnome(db);
+
+        assert_eq!(app.kept_packages, ["firefox"]);
+        assert_eq!(app.removal_packages, ["plasma-meta"]);
+        assert!(app.generate_script().contains("#   firefox"));
+    }
+
+    #[test]
+    fn diff_lists_shared_packages_only_as_kept() {
+        let db = local_db("diff", &[
+            ("plasma-meta", true, &[]),
+            ("sddm", true, &["qt6-base"]),
+            ("qt6-base", false, &[]),
+        ]);
+        let app = app_switching_kde_to_gnome(db);
+
+        let [removed, installed, kept] = app.diff_columns();
+        assert_eq!(removed, ["plasma-meta", "sddm"]);
+        assert_eq!(installed, ["gnome", "gdm"]);
+        assert_eq!(kept, ["qt6-base"]);
+    }
+
+    #[test]
+    fn install_only_removes_nothing() {
+        let db = local_db("install-only", &[("plasma-meta", true, &[])]);
+        let mut app = app_switching_kde_to_gnome(db);
+        app.set_source_profile(system::INSTALL_ONLY_PROFILE);
+
+        assert!(app.removal_packages.is_empty());
+        assert!(app.generate_script().contains("Skipping old DE removal (install only"));
+    }
+
+    #[test]
+    fn profile_packages_are_fetched_once() {
+        let runner = eos_runner();
+        let calls = runner.calls();
+        let db = local_db("cache", &[("plasma-meta", true, &[])]);
+        let mut app = app_switching_kde_to_gnome_with(runner, db);
+        app.next_de();
+        app.previous_de();
+
+        assert_eq!(calls.borrow().iter().filter(|c| *c == "eos-packagelist KDE-Desktop").count(), 1);
+    }
+
+    #[test]
+    fn missing_eos_packagelist_falls_back_to_builtin_manifests() {
+        let db = local_db("manifest", &[("sddm", true, &[]), ("konsole", true, &[]), ("bash", true, &[])]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "XFCE4-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert_eq!(app.removal_packages, ["konsole", "sddm"]);
+        assert!(app.generate_script().contains("INSTALL_PACKAGES=(\n    xfce4-session\n    xfwm4\n"));
+
+        // Switching back removes the desktop's real packages, not its group names.
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.local_db = local_db("manifest-xfce", &[("xfce4-session", true, &[]), ("xfwm4", true, &[]), ("thunar", true, &[])]);
+        app.set_source_profile("XFCE4-Desktop");
+        assert_eq!(app.removal_packages, ["xfce4-session", "xfwm4", "thunar"]);
+    }
+
+    #[test]
+    fn plasma_meta_is_removed_with_kde_without_eos_packagelist() {
+        let db = local_db("manifest-meta", &[
+            ("plasma-meta", true, &["plasma-desktop", "kate"]),
+            ("plasma-desktop", false, &[]),
+            ("kate", false, &[]),
+        ]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert!(app.kept_packages.is_empty());
+        assert_eq!(app.removal_packages, ["plasma-meta", "plasma-desktop", "kate"]);
+    }
+
+    #[test]
+    fn missing_eos_packagelist_falls_back_to_config_package_lists() {
+        let profiles = ProfileConfig::with_overrides(r#"
+            [profiles."KDE-Desktop"]
+            packages = ["plasma-meta", "qt6-base"]
+
+            [profiles."GNOME-Desktop"]
+            packages = ["gnome", "qt6-base"]
+        "#).unwrap();
+        let db = local_db("no-eos", &[("plasma-meta", true, &[]), ("qt6-base", false, &[])]);
+
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), profiles, db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert!(!app.eos_available);
+        assert!(!app.missing_install_list());
+        assert_eq!(app.removal_packages, ["plasma-meta"]);
+
+        let script = app.generate_script();
+        assert!(script.contains("INSTALL_PACKAGES=(\n    gnome\n    qt6-base\n)"));
+        assert!(!script.contains("eos-packagelist"));
+    }
+
+    #[test]
+    fn missing_package_list_aborts_the_script_early() {
+        let profiles = ProfileConfig::with_overrides(r#"
+            [profiles.Wayfire]
+            display_manager = "sddm"
+        "#).unwrap();
+        let db = local_db("no-list", &[]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), profiles, db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "Wayfire").unwrap();
+        app.refresh_package_sets();
+
+        assert!(app.missing_install_list());
+        let script = app.generate_script();
+        let abort = script.find("No package list is known for Wayfire").unwrap();
+        assert!(abort < script.find("# 1. REMOVE").unwrap());
+    }
+
+    #[test]
+    fn preview_cursor_on_an_array_line_highlights_its_package() {
+        let db = local_db("cursor", &[("plasma-meta", true, &[]), ("sddm", true, &[])]);
+        let mut app = app_switching_kde_to_gnome(db);
+        app.preview_focused = true;
+        app.preview_height = 10;
+
+        let script = app.generate_script();
+        let line = script.lines().position(|l| l == "    sddm").unwrap();
+        app.move_preview_cursor(line as isize);
+        assert_eq!(app.highlighted_package().as_deref(), Some("sddm"));
+        assert!(app.preview_scroll <= line && line < app.preview_scroll + app.preview_height);
+
+        app.move_preview_cursor(isize::MIN);
+        assert_eq!(app.highlighted_package(), None);
+    }
+
+    #[test]
+    fn space_estimate_counts_only_packages_that_change() {
+        let local = local_db("space", &[("plasma-meta", true, &[]), ("sddm", true, &[]), ("qt6-base", false, &[])]);
+        let sync = sync_db("space", "extra", &[("gnome", 1000, 5000), ("gdm", 200, 800), ("qt6-base", 50, 70)]);
+        let mut app = App::with_environment(Box::new(eos_runner()), &FakeProbe::new(), ProfileConfig::builtin(), local, sync).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        let space = app.space_estimate();
+        assert_eq!((space.download, space.installed), (1200, 5800));
+        assert_eq!(space.freed, 2 * 1048576);
+        assert!(space.unknown.is_empty());
+    }
+
+    #[test]
+    fn free_space_check_uses_the_estimate() {
+        let gib = 1024 * 1024 * 1024;
+        let small = SpaceEstimate { download: 1, installed: 1, ..SpaceEstimate::default() };
+        let large = SpaceEstimate { download: gib, installed: 3 * gib, ..SpaceEstimate::default() };
+
+        assert_eq!(script::required_free_mb(&small), script::PREFLIGHT_MIN_FREE_MB);
+        assert_eq!(script::required_free_mb(&large), 4096);
+        assert!(script::preflight_section(4096).contains("REQUIRED_FREE_MB=4096"));
+    }
+
+    #[test]
+    fn wayland_profiles_install_from_their_manifest() {
+        let runner = eos_runner();
+        let calls = runner.calls();
+        let db = local_db("wayland", &[("plasma-meta", true, &[])]);
+        let mut app = App::with_environment(Box::new(runner), &FakeProbe::new(), ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "Sway").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert!(!calls.borrow().iter().any(|c| c == "eos-packagelist Sway"));
+        assert!(app.install_packages.contains(&"xdg-desktop-portal-wlr".to_string()));
+        let script = app.generate_script();
+        assert!(script.contains("/usr/share/wayland-sessions/sway.desktop"));
+        assert!(script.contains("sudo systemctl enable greetd"));
+    }
+
+    #[test]
+    fn cosmic_uses_its_own_greeter() {
+        let profiles = ProfileConfig::builtin();
+        assert_eq!(profiles.display_manager("COSMIC-Desktop"), "cosmic-greeter");
+    }
+
+    #[test]
+    fn script_installs_the_display_manager_and_a_greeter() {
+        let profiles = ProfileConfig::with_overrides(r#"
+            [profiles.Wayfire]
+            display_manager = "lightdm"
+            packages = ["wayfire"]
+        "#).unwrap();
+        let db = local_db("dm", &[]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), profiles, db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "Wayfire").unwrap();
+        app.refresh_package_sets();
+
+        assert_eq!(app.dm_packages, ["lightdm", "lightdm-gtk-greeter"]);
+        let script = app.generate_script();
+        assert!(script.contains("DM_PACKAGES=(\n    lightdm\n    lightdm-gtk-greeter\n)"));
+        assert!(script.contains("sudo systemctl enable lightdm.service"));
+    }
+
+    #[test]
+    fn display_manager_already_in_the_profile_is_not_listed_twice() {
+        let db = local_db("dm-shared", &[("sddm", true, &[])]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "river").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert!(app.dm_packages.is_empty());
+        assert!(app.generate_script().contains("sudo systemctl enable ly@tty2.service"));
+        assert!(app.removal_packages.contains(&"sddm".to_string()));
+    }
+
+    #[test]
+    fn chosen_display_manager_replaces_the_profile_default() {
+        let db = local_db("dm-choice", &[("plasma-meta", true, &[]), ("sddm", true, &[])]);
+        let mut app = app_switching_kde_to_gnome(db);
+        let sddm = display_managers::DISPLAY_MANAGERS.iter().position(|d| d.name == "sddm").unwrap();
+        app.selected_dm_index = Some(sddm);
+        app.refresh_package_sets();
+
+        assert_eq!(app.target_display_manager(), "sddm");
+        assert!(!app.removal_packages.contains(&"sddm".to_string()));
+        assert!(app.generate_script().contains("sudo systemctl enable sddm.service"));
+    }
+
+    #[test]
+    fn cycling_display_managers_returns_to_the_profile_default() {
+        let db = local_db("dm-cycle", &[]);
+        let mut app = app_switching_kde_to_gnome(db);
+        for _ in 0..display_managers::DISPLAY_MANAGERS.len() {
+            app.cycle_display_manager();
+            assert!(app.selected_dm_index.is_some());
+        }
+        app.cycle_display_manager();
+        assert_eq!(app.target_display_manager(), "gdm");
+    }
+
+    #[test]
+    fn warns_about_display_managers_that_cannot_start() {
+        let db = local_db("dm-warn", &[]);
+        let mut app = app_switching_kde_to_gnome_on(FakeProbe::new(), eos_runner(), db);
+        assert!(app.display_manager_warnings().iter().any(|w| w.contains("systemd-logind")));
+
+        app.selected_dm_index = display_managers::DISPLAY_MANAGERS.iter().position(|d| d.name == "cosmic-greeter");
+        app.refresh_package_sets();
+        assert!(app.display_manager_warnings().iter().any(|w| w.contains("cosmic-comp")));
+
+        app.selected_dm_index = display_managers::DISPLAY_MANAGERS.iter().position(|d| d.name == "lightdm");
+        app.refresh_package_sets();
+        assert!(app.display_manager_warnings().iter().any(|w| w.contains("lightdm-gtk-greeter will be installed")));
+    }
+
+    #[test]
+    fn alongside_mode_skips_removal_and_writes_it_to_the_finalize_script() {
+        let db = local_db("alongside", &[("plasma-meta", true, &[]), ("sddm", true, &[])]);
+        let mut app = app_switching_kde_to_gnome(db);
+        app.cycle_switch_mode();
+
+        assert_eq!(app.switch_mode, SwitchMode::Alongside);
+        assert!(app.switch_removal_packages().is_empty());
+        let script = app.generate_script();
+        assert!(!script.contains("REMOVE_PACKAGES"));
+        assert!(script.contains("# 1. KEEP THE CURRENT DE"));
+        assert!(script.contains("sudo systemctl enable gdm.service"));
+
+        let finalize = app.generate_finalize_script();
+        assert!(finalize.contains("REMOVE_PACKAGES=(\n    plasma-meta\n    sddm\n)"));
+        assert!(!finalize.contains("systemctl enable"));
+    }
+
+    #[test]
+    fn keeping_the_display_manager_moves_the_switch_to_the_finalize_script() {
+        let db = local_db("alongside-dm", &[("plasma-meta", true, &[])]);
+        let mut app = app_switching_kde_to_gnome_from_sddm(db);
+        app.switch_mode = SwitchMode::AlongsideKeepDm;
+
+        let script = app.generate_script();
+        assert!(script.contains("Keeping Display Manager: sddm"));
+        assert!(!script.contains("systemctl enable"));
+        assert!(app.generate_finalize_script().contains("sudo systemctl enable gdm.service"));
+    }
+
+    #[test]
+    fn finalize_script_sits_next_to_the_switch_script() {
+        let db = local_db("finalize-name", &[]);
+        let mut app = app_switching_kde_to_gnome(db);
+        app.input_buffer = "/tmp/switch.sh".to_string();
+        assert_eq!(app.finalize_filename(), "/tmp/switch_finalize.sh");
+    }
+
+    fn app_removing_kde(db: LocalDb) -> App {
+        let mut app = app_switching_kde_to_gnome_from_sddm(db);
+        app.selected_de_index = app.available_des.iter().position(|p| p == "KDE-Desktop").unwrap();
+        app.toggle_cleanup_mode();
+        app
+    }
+
+    #[test]
+    fn cleanup_keeps_packages_other_installed_profiles_need() {
+        let db = local_db("cleanup", &[
+            ("plasma-meta", true, &[]),
+            ("kate", true, &[]),
+            ("sddm", true, &[]),
+            ("qt6-base", false, &[]),
+            ("plasma-integration", false, &["qt6-base"]),
+            ("gnome-shell", true, &[]),
+            ("gdm", true, &["plasma-integration"]),
+        ]);
+        let app = app_removing_kde(db);
+
+        assert_eq!(app.other_installed_profiles, ["GNOME-Desktop"]);
+        assert_eq!(app.removal_packages, ["plasma-meta", "kate", "sddm"]);
+        assert_eq!(app.shared_packages, ["qt6-base", "plasma-integration"]);
+        assert!(app.install_packages.is_empty());
+        assert!(!app.missing_install_list());
+    }
+
+    #[test]
+    fn cleanup_script_removes_the_profile_and_orphans_without_installing() {
+        let db = local_db("cleanup-script", &[("plasma-meta", true, &[]), ("sddm", true, &[]), ("qt6-base", false, &[])]);
+        let app = app_removing_kde(db);
+
+        assert!(app.other_installed_profiles.is_empty());
+        assert_eq!(app.removal_packages, ["plasma-meta", "sddm", "qt6-base"]);
+        assert_eq!(app.input_buffer, "./de_switcher_remove_KDE.sh");
+        assert!(!app.writes_rollback() && !app.writes_finalize());
+
+        let script = app.generate_script();
+        assert!(script.contains("# 1. REMOVE KDE-Desktop PACKAGES"));
+        assert!(script.contains("mapfile -t ORPHANS < <(pacman -Qtdq)"));
+        assert!(script.contains("sudo pacman -Rns \"${ORPHANS[@]}\""));
+        assert!(script.contains("Warning: sddm was removed"));
+        assert!(!script.contains("-S --needed"));
+        assert!(!script.contains("systemctl enable"));
+    }
+
+    #[test]
+    fn current_display_manager_is_disabled_before_its_package_is_removed() {
+        let db = local_db("dm-order", &[("plasma-meta", true, &[]), ("sddm", true, &[])]);
+        let app = app_switching_kde_to_gnome_from_sddm(db);
+
+        let script = app.generate_script();
+        let disable = script.find("sudo systemctl disable --force sddm").unwrap();
+        let removal = script.find("REMOVE_PACKAGES=(").unwrap();
+        assert!(disable < removal);
+        assert!(script.contains("REMOVE_PACKAGES=(\n    plasma-meta\n    sddm\n)"));
+    }
+
+    #[test]
+    fn rollback_disables_the_new_display_manager_before_removing_it() {
+        let db = local_db("rollback-dm", &[("plasma-meta", true, &[]), ("sddm", true, &[])]);
+        let app = app_switching_kde_to_gnome_from_sddm(db);
+
+        let rollback = app.generate_rollback_script();
+        let disable = rollback.find("sudo systemctl disable --force gdm.service").unwrap();
+        let removal = rollback.find("-Rns \"${NEW_PACKAGES[@]}\"").unwrap();
+        assert!(disable < removal);
+        assert!(rollback.contains("PREVIOUS_DM=\"sddm\""));
+        assert!(rollback.contains("sudo systemctl enable --force \"$PREVIOUS_DM\""));
+    }
+
+    #[test]
+    fn rollback_only_removes_the_target_packages_added_by_the_switch() {
+        let db = local_db("rollback-new", &[("plasma-meta", true, &[])]);
+        let rollback = app_switching_kde_to_gnome(db).generate_rollback_script();
+
+        assert!(rollback.contains("NEW_PACKAGES=(\n    gnome\n    gdm\n    qt6-base\n)"));
+        assert!(rollback.contains(
+            "comm -12 <(printf '%s\\n' \"${NEW_PACKAGES[@]}\" | sort) <(comm -13 \"$STATE_DIR/installed-packages.txt\" <(pacman -Qq | sort))"
+        ));
+    }
+
+    #[test]
+    fn rollback_reinstalls_removed_dependencies_as_dependencies() {
+        let db = local_db("rollback-deps", &[("plasma-meta", true, &[])]);
+        let rollback = app_switching_kde_to_gnome(db).generate_rollback_script();
+
+        assert!(rollback.contains("comm -23 \"$STATE_DIR/installed-packages.txt\" <(pacman -Qq | sort) | comm -23 - \"$STATE_DIR/explicit-packages.txt\""));
+        assert!(rollback.contains("sudo pacman -S --needed --asdeps \"${REMOVED_DEPENDENCIES[@]}\""));
+        let explicit = rollback.find("-S --needed \"${REMOVED_PACKAGES[@]}\"").unwrap();
+        assert!(rollback.find("-Rns").unwrap() < explicit);
+    }
+
+    #[test]
+    fn greeter_is_added_when_only_a_package_group_is_installed() {
+        let db = local_db("group-greeter", &[]);
+        let runner = eos_runner().with_output("eos-packagelist i3-Window-Manager", "i3-wm\ni3status\nlightdm\nlightdm-gtk-greeter\n");
+        let mut app = app_switching_kde_to_gnome_with(runner, db);
+        app.selected_de_index = app.available_des.iter().position(|p| p == "i3-Window-Manager").unwrap();
+        app.refresh_package_sets();
+
+        assert_eq!(app.install_packages, ["i3-gaps"]);
+        assert_eq!(app.dm_packages, ["lightdm", "lightdm-gtk-greeter"]);
+        assert!(app.display_manager_warnings().iter().any(|w| w.contains("lightdm-gtk-greeter will be installed")));
+    }
+
+    #[test]
+    fn new_packages_list_the_display_manager_once() {
+        let db = local_db("new-dm-once", &[]);
+        let app = app_switching_kde_to_gnome(db);
+
+        assert_eq!(app.new_packages, ["gnome", "gdm", "qt6-base"]);
+        assert!(app.generate_rollback_script().contains("NEW_PACKAGES=(\n    gnome\n    gdm\n    qt6-base\n)"));
+    }
+
+    #[test]
+    fn manifests_are_installed_instead_of_package_groups_without_eos_packagelist() {
+        let db = local_db("no-eos-group", &[]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), ProfileConfig::builtin(), db, SyncDb::default()).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "i3-Window-Manager").unwrap();
+        app.refresh_package_sets();
+
+        assert_eq!(app.install_packages, manifests::manifest_for("i3-Window-Manager"));
+        assert!(app.dm_packages.is_empty());
+        assert!(!app.generate_script().contains("i3-gaps"));
+    }
+
+    #[test]
+    fn package_groups_are_expanded_through_the_sync_databases() {
+        let runner = eos_runner().with_output("eos-packagelist i3-Window-Manager", "i3-wm\nlightdm\n");
+        let sync = sync_db_with_groups("groups", "extra", &[
+            ("i3-wm", 100, 200, &["i3-gaps"]),
+            ("i3status", 10, 20, &["i3-gaps"]),
+            ("lightdm", 10, 20, &[]),
+        ]);
+        let mut app = App::with_environment(Box::new(runner), &FakeProbe::new(), ProfileConfig::builtin(), local_db("groups", &[]), sync).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "i3-Window-Manager").unwrap();
+        app.refresh_package_sets();
+
+        assert_eq!(app.install_packages, ["i3-wm", "i3status"]);
+        assert!(!app.generate_script().contains("unexpanded"));
+
+        app.sync_db = sync_db("groups-missing", "extra", &[("i3-wm", 100, 200)]);
+        app.refresh_package_sets();
+        assert_eq!(app.install_packages, ["i3-gaps"]);
+        assert!(app.generate_script().contains("# i3-gaps is not in the sync databases, so it is passed to pacman unexpanded"));
+
+        app.sync_db = SyncDb::default();
+        app.refresh_package_sets();
+        assert!(app.generate_script().contains("# The sync databases were not readable, so package groups are passed to pacman unexpanded."));
+    }
+
+    #[test]
+    fn groups_inside_package_lists_are_expanded_for_install_and_removal() {
+        let profiles = ProfileConfig::with_overrides(r#"
+            [profiles."KDE-Desktop"]
+            packages = ["plasma", "kate"]
+
+            [profiles."GNOME-Desktop"]
+            packages = ["gnome", "gdm"]
+        "#).unwrap();
+        let local = local_db("list-groups", &[("plasma-desktop", true, &[]), ("kwin", false, &[]), ("kate", true, &[])]);
+        let sync = sync_db_with_groups("list-groups", "extra", &[
+            ("plasma-desktop", 1, 1, &["plasma"]),
+            ("kwin", 1, 1, &["plasma"]),
+            ("gnome-shell", 1, 1, &["gnome"]),
+            ("nautilus", 1, 1, &["gnome"]),
+            ("gdm", 1, 1, &["gnome"]),
+        ]);
+        let mut app = App::with_environment(Box::new(ScriptedRunner::new()), &FakeProbe::new(), profiles, local, sync).unwrap();
+        app.selected_de_index = app.available_des.iter().position(|p| p == "GNOME-Desktop").unwrap();
+        app.set_source_profile("KDE-Desktop");
+
+        assert_eq!(app.install_packages, ["gdm", "gnome-shell", "nautilus"]);
+        assert_eq!(app.removal_packages, ["kwin", "plasma-desktop", "kate"]);
+        let script = app.generate_script();
+        assert!(script.contains("INSTALL_PACKAGES=(\n    gdm\n    gnome-shell\n    nautilus\n)"));
+        assert!(!script.contains("unexpanded"));
+    }
+
+    #[test]
+    fn install_only_writes_no_finalize_script() {
+        let db = local_db("alongside-none", &[("plasma-meta", true, &[])]);
+        let mut app = app_switching_kde_to_gnome_from_sddm(db);
+        app.switch_mode = SwitchMode::AlongsideKeepDm;
+        app.set_source_profile(system::INSTALL_ONLY_PROFILE);
+
+        assert!(!app.writes_finalize());
+        assert!(!app.keeps_current_dm());
+        let script = app.generate_script();
+        assert!(!script.contains("_finalize.sh"));
+        assert!(script.contains("Skipping old DE removal (install only"));
+        assert!(script.contains("sudo systemctl enable gdm.service"));
+    }
 }
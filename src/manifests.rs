@@ -0,0 +1,160 @@
+/// Built-in package lists per profile, used when eos-packagelist is not installed
+/// and always for the Wayland profiles, which it does not know about. Each one covers
+/// the desktop itself, its display manager and greeter, and the basics the
+/// EndeavourOS profiles ship (network applet, portal, user dirs).
+/// Entries are package names, never pacman groups such as `xfce4`: the removal set is
+/// matched against the local database, where no package is named after its group.
+/// A `packages` list in `profiles.toml` replaces the built-in one.
+pub const PROFILE_MANIFESTS: &[(&str, &[&str])] = &[
+    ("KDE-Desktop", &[
+        "plasma-meta", "plasma-desktop", "plasma-nm", "plasma-pa", "powerdevil", "bluedevil", "kscreen",
+        "kdeplasma-addons", "breeze-gtk", "kde-gtk-config", "kwalletmanager", "konsole",
+        "dolphin", "kate", "ark", "spectacle", "gwenview", "xdg-desktop-portal-kde",
+        "sddm", "sddm-kcm",
+    ]),
+    ("GNOME-Desktop", &[
+        "gnome-shell", "gnome-session", "gnome-settings-daemon", "gnome-control-center",
+        "gnome-console", "gnome-text-editor", "gnome-tweaks", "gnome-keyring",
+        "gnome-backgrounds", "nautilus", "file-roller", "loupe", "xdg-desktop-portal-gnome",
+        "xdg-user-dirs-gtk", "gdm",
+    ]),
+    ("XFCE4-Desktop", &[
+        "xfce4-session", "xfwm4", "xfwm4-themes", "xfce4-panel", "xfce4-settings", "xfconf",
+        "xfdesktop", "xfce4-power-manager", "xfce4-appfinder", "xfce4-terminal", "thunar",
+        "thunar-volman", "thunar-archive-plugin", "tumbler", "garcon", "exo", "mousepad",
+        "ristretto", "xfce4-notifyd", "xfce4-screenshooter", "xfce4-taskmanager",
+        "xfce4-pulseaudio-plugin", "xfce4-whiskermenu-plugin", "network-manager-applet",
+        "pavucontrol", "xdg-user-dirs-gtk", "xdg-desktop-portal-gtk", "lightdm",
+        "lightdm-gtk-greeter", "lightdm-gtk-greeter-settings",
+    ]),
+    ("Cinnamon-Desktop", &[
+        "cinnamon", "cinnamon-translations", "nemo-fileroller", "gnome-terminal", "xed",
+        "xreader", "xdg-user-dirs-gtk", "xdg-desktop-portal-gtk", "lightdm",
+        "lightdm-slick-greeter",
+    ]),
+    ("MATE-Desktop", &[
+        "mate-session-manager", "mate-desktop", "marco", "mate-panel", "mate-menus",
+        "mate-control-center", "mate-settings-daemon", "mate-notification-daemon",
+        "mate-polkit", "mate-backgrounds", "mate-themes", "mate-icon-theme", "caja",
+        "mate-terminal", "pluma", "eom", "atril", "engrampa", "mate-media",
+        "mate-power-manager", "mate-system-monitor", "mate-screensaver",
+        "network-manager-applet", "xdg-user-dirs-gtk", "xdg-desktop-portal-gtk", "lightdm",
+        "lightdm-gtk-greeter",
+    ]),
+    ("Budgie-Desktop", &[
+        "budgie-desktop", "budgie-control-center", "budgie-desktop-view",
+        "budgie-screensaver", "nemo", "gnome-terminal", "network-manager-applet",
+        "xdg-user-dirs-gtk", "xdg-desktop-portal-gtk", "lightdm", "lightdm-slick-greeter",
+    ]),
+    ("LXQT-Desktop", &[
+        "lxqt-session", "lxqt-panel", "lxqt-runner", "lxqt-config", "lxqt-globalkeys",
+        "lxqt-notificationd", "lxqt-policykit", "lxqt-powermanagement", "lxqt-qtplugin",
+        "lxqt-themes", "lxqt-about", "lxqt-admin", "lxqt-sudo", "lxqt-archiver", "openbox",
+        "pcmanfm-qt", "qterminal", "lximage-qt", "screengrab", "breeze-icons", "nm-tray",
+        "pavucontrol-qt", "featherpad", "xdg-desktop-portal-lxqt", "xdg-user-dirs", "sddm",
+    ]),
+    ("LXDE-Desktop", &[
+        "lxsession", "lxde-common", "lxpanel", "openbox", "pcmanfm", "lxappearance",
+        "lxappearance-obconf", "lxde-icon-theme", "lxinput", "lxrandr", "lxtask",
+        "lxterminal", "lxhotkey", "gpicview", "network-manager-applet", "pavucontrol",
+        "xdg-user-dirs-gtk", "lightdm", "lightdm-gtk-greeter",
+    ]),
+    ("i3-Window-Manager", &[
+        "i3-wm", "i3status", "i3lock", "dmenu", "xss-lock", "picom", "feh", "dunst",
+        "alacritty", "network-manager-applet", "xdg-user-dirs", "lightdm",
+        "lightdm-gtk-greeter",
+    ]),
+    ("COSMIC-Desktop", &[
+        "cosmic-session", "cosmic-comp", "cosmic-panel", "cosmic-launcher",
+        "cosmic-applets", "cosmic-settings", "cosmic-files", "cosmic-terminal",
+        "cosmic-text-editor", "xdg-desktop-portal-cosmic", "cosmic-greeter",
+    ]),
+    ("Hyprland", &[
+        "hyprland", "xdg-desktop-portal-hyprland", "xdg-desktop-portal-gtk", "hyprpolkitagent",
+        "hyprpaper", "hyprlock", "hypridle", "waybar", "wofi", "kitty", "mako", "qt5-wayland",
+        "qt6-wayland", "network-manager-applet", "xdg-user-dirs", "sddm",
+    ]),
+    ("Sway", &[
+        "sway", "swaybg", "swayidle", "swaylock", "xdg-desktop-portal-wlr",
+        "xdg-desktop-portal-gtk", "polkit-gnome", "waybar", "wofi", "foot", "mako",
+        "network-manager-applet", "xdg-user-dirs", "greetd", "greetd-tuigreet",
+    ]),
+    ("niri", &[
+        "niri", "xdg-desktop-portal-gnome", "xdg-desktop-portal-gtk", "gnome-keyring",
+        "polkit-gnome", "xwayland-satellite", "swaybg", "swaylock", "waybar", "fuzzel",
+        "alacritty", "mako", "network-manager-applet", "xdg-user-dirs", "greetd",
+        "greetd-tuigreet",
+    ]),
+    ("river", &[
+        "river", "xdg-desktop-portal-wlr", "xdg-desktop-portal-gtk", "polkit-gnome",
+        "swaybg", "swaylock", "waybar", "fuzzel", "foot", "mako", "network-manager-applet",
+        "xdg-user-dirs", "ly",
+    ]),
+];
+
+pub fn manifest_for(profile: &str) -> &'static [&'static str] {
+    PROFILE_MANIFESTS.iter()
+        .find(|(p, _)| *p == profile)
+        .map_or(&[], |(_, packages)| *packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DE_DM_MAP, WAYLAND_PROFILES, system};
+
+    #[test]
+    fn every_builtin_profile_has_a_manifest() {
+        for (profile, _) in DE_DM_MAP {
+            assert!(!manifest_for(profile).is_empty(), "no manifest for {}", profile);
+        }
+    }
+
+    #[test]
+    fn manifests_include_their_display_manager() {
+        for (profile, dm) in DE_DM_MAP {
+            assert!(manifest_for(profile).contains(dm), "{} manifest does not install {}", profile, dm);
+        }
+    }
+
+    #[test]
+    fn wayland_manifests_include_a_portal() {
+        for profile in WAYLAND_PROFILES {
+            assert!(
+                manifest_for(profile).iter().any(|p| p.starts_with("xdg-desktop-portal-")),
+                "{} manifest has no xdg-desktop-portal backend", profile
+            );
+        }
+    }
+
+    #[test]
+    fn manifests_include_their_marker_package() {
+        for (profile, _) in DE_DM_MAP {
+            let marker = system::profile_marker(profile).unwrap();
+            assert!(manifest_for(profile).contains(&marker), "{} manifest does not install {}", profile, marker);
+        }
+    }
+
+    #[test]
+    fn manifests_list_packages_instead_of_groups() {
+        const GROUPS: &[&str] = &[
+            "plasma", "kde-applications", "gnome", "gnome-extra", "xfce4", "xfce4-goodies",
+            "mate", "mate-extra", "lxqt", "lxde", "lxde-gtk3", "cosmic",
+        ];
+        for (profile, packages) in PROFILE_MANIFESTS {
+            for group in GROUPS {
+                assert!(!packages.contains(group), "{} manifest lists the {} group", profile, group);
+            }
+        }
+    }
+
+    #[test]
+    fn manifests_have_no_duplicates() {
+        for (profile, packages) in PROFILE_MANIFESTS {
+            let mut sorted = packages.to_vec();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted.len(), packages.len(), "duplicate package in {}", profile);
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::system;
+
+pub const LOCAL_DB_PATH: &str = "/var/lib/pacman/local";
+pub const SYNC_DB_PATH: &str = "/var/lib/pacman/sync";
+
+#[derive(Debug, Clone)]
+pub struct LocalPackage {
+    pub name: String,
+    /// `%REASON%` is absent or `0` for explicitly installed packages, `1` for dependencies.
+    pub explicit: bool,
+    pub depends: Vec<String>,
+    pub version: String,
+    pub description: String,
+    /// `%SIZE%`, the installed size in bytes.
+    pub installed_size: u64,
+}
+
+/// Installed packages, read from the `desc` files of pacman's local database.
+#[derive(Debug, Default)]
+pub struct LocalDb {
+    packages: HashMap<String, LocalPackage>,
+    providers: HashMap<String, String>,
+    required_by: HashMap<String, Vec<String>>,
+}
+
+impl LocalDb {
+    /// Reads the system database, or returns an empty one if it is not readable
+    /// (e.g. when not running on an Arch-based system).
+    pub fn load() -> Self {
+        LocalDb::load_from(Path::new(LOCAL_DB_PATH)).unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        let mut db = LocalDb::default();
+
+        for entry in fs::read_dir(path)? {
+            let desc_path = entry?.path().join("desc");
+            let Ok(content) = fs::read_to_string(&desc_path) else {
+                continue;
+            };
+
+            let fields = parse_desc(&content);
+            let Some(name) = first_field(&fields, "NAME") else {
+                continue;
+            };
+
+            for provided in fields.get("PROVIDES").into_iter().flatten() {
+                db.providers.insert(strip_version(provided).to_string(), name.to_string());
+            }
+
+            db.packages.insert(name.to_string(), LocalPackage {
+                name: name.to_string(),
+                explicit: first_field(&fields, "REASON").is_none_or(|r| r == "0"),
+                depends: fields.get("DEPENDS").into_iter().flatten()
+                    .map(|d| strip_version(d).to_string())
+                    .collect(),
+                version: first_field(&fields, "VERSION").unwrap_or_default().to_string(),
+                description: first_field(&fields, "DESC").unwrap_or_default().to_string(),
+                installed_size: size_field(&fields, "SIZE"),
+            });
+        }
+
+        let mut required_by: HashMap<String, Vec<String>> = HashMap::new();
+        for pkg in db.packages.values() {
+            for dep in &pkg.depends {
+                if let Some(provider) = db.resolve(dep) {
+                    required_by.entry(provider.to_string()).or_default().push(pkg.name.clone());
+                }
+            }
+        }
+        db.required_by = required_by;
+
+        Ok(db)
+    }
+
+    pub fn is_installed(&self, name: &str) -> bool {
+        self.packages.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LocalPackage> {
+        self.packages.get(name)
+    }
+
+    /// Installed packages that directly depend on `name`, sorted.
+    pub fn required_by(&self, name: &str) -> Vec<String> {
+        let mut names = self.required_by.get(name).cloned().unwrap_or_default();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Maps a dependency name to the installed package satisfying it, directly or via `%PROVIDES%`.
+    fn resolve(&self, dep: &str) -> Option<&str> {
+        if let Some(pkg) = self.packages.get(dep) {
+            return Some(pkg.name.as_str());
+        }
+        self.providers.get(dep).map(|p| p.as_str())
+    }
+
+    /// `roots` plus every installed package they depend on, transitively.
+    fn dependency_closure<'a>(&'a self, roots: impl IntoIterator<Item = &'a str>) -> HashSet<&'a str> {
+        self.walk(roots, |name| {
+            self.packages.get(name)
+                .map(|pkg| pkg.depends.iter().filter_map(|d| self.resolve(d)).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    /// `roots` plus every installed package depending on them, transitively. This is
+    /// what `pacman -Rc` cascades into.
+    fn dependents_closure<'a>(&'a self, roots: impl IntoIterator<Item = &'a str>) -> HashSet<&'a str> {
+        self.walk(roots, |name| {
+            self.required_by.get(name)
+                .map(|names| names.iter().map(|n| n.as_str()).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn walk<'a, F>(&'a self, roots: impl IntoIterator<Item = &'a str>, next: F) -> HashSet<&'a str>
+    where
+        F: Fn(&str) -> Vec<&'a str>,
+    {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = roots.into_iter().filter(|r| self.is_installed(r)).collect();
+
+        while let Some(name) = queue.pop_front() {
+            if seen.insert(name) {
+                queue.extend(next(name));
+            }
+        }
+
+        seen
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncPackage {
+    pub repository: String,
+    pub version: String,
+    pub description: String,
+    /// `%CSIZE%`, the download size in bytes.
+    pub download_size: u64,
+    /// `%ISIZE%`, the installed size in bytes.
+    pub installed_size: u64,
+    /// `%GROUPS%`, the package groups it belongs to.
+    pub groups: Vec<String>,
+}
+
+/// Packages available from the configured repositories, read from the sync database
+/// archives (`core.db`, `extra.db`, ...). The first repository listing a package wins.
+#[derive(Debug, Default)]
+pub struct SyncDb {
+    packages: HashMap<String, SyncPackage>,
+}
+
+impl SyncDb {
+    /// Reads the system sync databases, or returns an empty one if none are readable.
+    pub fn load() -> Self {
+        SyncDb::load_from(Path::new(SYNC_DB_PATH)).unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        let mut db_paths: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "db"))
+            .collect();
+        db_paths.sort();
+
+        let mut db = SyncDb::default();
+        for db_path in db_paths {
+            let repository = db_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            // A single unreadable or unsupported (e.g. zstd-compressed) database is skipped.
+            let _ = db.read_archive(&db_path, &repository);
+        }
+
+        Ok(db)
+    }
+
+    fn read_archive(&mut self, path: &Path, repository: &str) -> io::Result<()> {
+        let mut data = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut data)?;
+
+        let reader: Box<dyn Read + '_> = if data.starts_with(&[0x1f, 0x8b]) {
+            Box::new(GzDecoder::new(data.as_slice()))
+        } else {
+            Box::new(data.as_slice())
+        };
+
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.path()?.ends_with("desc") {
+                continue;
+            }
+
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            let fields = parse_desc(&content);
+            let Some(name) = first_field(&fields, "NAME") else {
+                continue;
+            };
+
+            self.packages.entry(name.to_string()).or_insert_with(|| SyncPackage {
+                repository: repository.to_string(),
+                version: first_field(&fields, "VERSION").unwrap_or_default().to_string(),
+                description: first_field(&fields, "DESC").unwrap_or_default().to_string(),
+                download_size: size_field(&fields, "CSIZE"),
+                installed_size: size_field(&fields, "ISIZE"),
+                groups: fields.get("GROUPS").cloned().unwrap_or_default(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SyncPackage> {
+        self.packages.get(name)
+    }
+
+    /// Packages in `group`, sorted, or none if no sync database lists the group.
+    pub fn group_members(&self, group: &str) -> Vec<String> {
+        let mut members: Vec<String> = self.packages.iter()
+            .filter(|(_, pkg)| pkg.groups.iter().any(|g| g == group))
+            .map(|(name, _)| name.clone())
+            .collect();
+        members.sort();
+        members
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+}
+
+/// What the inspector shows for one package, from the local database if it is
+/// installed and from the sync databases otherwise.
+#[derive(Debug, Clone)]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub installed_size: u64,
+    pub installed: bool,
+    /// Repository offering the package, if any sync database lists it.
+    pub repository: Option<String>,
+    pub required_by: Vec<String>,
+}
+
+pub fn package_info(name: &str, local: &LocalDb, sync: &SyncDb) -> Option<PackageInfo> {
+    let repository = sync.get(name).map(|pkg| pkg.repository.clone());
+
+    if let Some(pkg) = local.get(name) {
+        return Some(PackageInfo {
+            name: name.to_string(),
+            version: pkg.version.clone(),
+            description: pkg.description.clone(),
+            installed_size: pkg.installed_size,
+            installed: true,
+            repository,
+            required_by: local.required_by(name),
+        });
+    }
+
+    sync.get(name).map(|pkg| PackageInfo {
+        name: name.to_string(),
+        version: pkg.version.clone(),
+        description: pkg.description.clone(),
+        installed_size: pkg.installed_size,
+        installed: false,
+        repository,
+        required_by: Vec::new(),
+    })
+}
+
+/// Disk impact of a switch, in bytes. Only the listed packages are counted, not the
+/// dependencies pacman pulls in or cascades into.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpaceEstimate {
+    /// `%CSIZE%` of the packages to install that are not installed yet.
+    pub download: u64,
+    /// `%ISIZE%` of the same packages.
+    pub installed: u64,
+    /// Local `%SIZE%` of the packages to remove.
+    pub freed: u64,
+    /// Packages to install that no sync database lists, e.g. package groups.
+    pub unknown: Vec<String>,
+}
+
+pub fn space_estimate(install: &[String], remove: &[String], local: &LocalDb, sync: &SyncDb) -> SpaceEstimate {
+    let mut estimate = SpaceEstimate::default();
+
+    for name in install.iter().filter(|name| !local.is_installed(name)) {
+        match sync.get(name) {
+            Some(pkg) => {
+                estimate.download += pkg.download_size;
+                estimate.installed += pkg.installed_size;
+            }
+            None => estimate.unknown.push(name.clone()),
+        }
+    }
+    estimate.freed = remove.iter()
+        .filter_map(|name| local.get(name))
+        .map(|pkg| pkg.installed_size)
+        .sum();
+
+    estimate
+}
+
+/// Parses a pacman `desc` file into its `%SECTION%` blocks.
+pub fn parse_desc(content: &str) -> HashMap<String, Vec<String>> {
+    let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            current = None;
+        } else if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+            let key = line.trim_matches('%').to_string();
+            fields.entry(key.clone()).or_default();
+            current = Some(key);
+        } else if let Some(key) = &current {
+            fields.entry(key.clone()).or_default().push(line.to_string());
+        }
+    }
+
+    fields
+}
+
+fn first_field<'a>(fields: &'a HashMap<String, Vec<String>>, key: &str) -> Option<&'a str> {
+    fields.get(key).and_then(|v| v.first()).map(|s| s.as_str())
+}
+
+fn size_field(fields: &HashMap<String, Vec<String>>, key: &str) -> u64 {
+    first_field(fields, key).and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+/// Drops a version constraint such as `>=1.2` or `=6.7-1` from a dependency or provide.
+fn strip_version(spec: &str) -> &str {
+    spec.split(['<', '>', '=']).next().unwrap_or(spec)
+}
+
+/// Packages from `current` that are installed and not also part of `target`,
+/// in the order they appear in `current`.
+pub fn removal_set(current: &[String], target: &[String], db: &LocalDb) -> Vec<String> {
+    let target: HashSet<&str> = target.iter().map(|s| s.as_str()).collect();
+    let mut seen = HashSet::new();
+
+    current.iter()
+        .filter(|pkg| !target.contains(pkg.as_str()) && db.is_installed(pkg))
+        .filter(|pkg| seen.insert(pkg.as_str()))
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RemovalPlan {
+    pub remove: Vec<String>,
+    /// Explicitly installed packages outside the profiles that `-Rcs` would otherwise cascade into.
+    pub kept: Vec<String>,
+}
+
+/// Shrinks `candidates` so that `pacman -Rcs` does not take explicitly installed user
+/// packages down with it. Any candidate such a package depends on is left installed.
+/// Packages listed in `profile_packages`, and the marker and meta-packages of `profiles`
+/// (e.g. `plasma-meta`), are never user packages.
+pub fn protect_explicit(candidates: Vec<String>, profile_packages: &HashSet<&str>, profiles: &[&str], db: &LocalDb) -> RemovalPlan {
+    let cascade = db.dependents_closure(candidates.iter().map(|c| c.as_str()));
+    let own: HashSet<&str> = profiles.iter().flat_map(|p| system::profile_own_packages(p)).collect();
+
+    let mut kept: Vec<String> = cascade.iter()
+        .filter(|name| !profile_packages.contains(*name) && !own.contains(*name))
+        .filter(|name| db.packages.get(**name).is_some_and(|pkg| pkg.explicit))
+        .map(|name| name.to_string())
+        .collect();
+    kept.sort();
+
+    let needed = db.dependency_closure(kept.iter().map(|k| k.as_str()));
+    let remove = candidates.into_iter()
+        .filter(|c| !needed.contains(c.as_str()))
+        .collect();
+
+    RemovalPlan { remove, kept }
+}
+
+/// Throwaway pacman databases for tests.
+#[cfg(test)]
+pub mod testing {
+    use super::{LocalDb, SyncDb};
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Writes a throwaway local pacman database. Each entry is (name, explicit, depends).
+    pub fn local_db(name: &str, packages: &[(&str, bool, &[&str])]) -> LocalDb {
+        let root: PathBuf = env::temp_dir().join(format!("de-switcher-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        for (pkg, explicit, depends) in packages {
+            let dir = root.join(format!("{}-1.0-1", pkg));
+            fs::create_dir_all(&dir).unwrap();
+            let desc = format!(
+                "%NAME%\n{}\n\n%VERSION%\n1.0-1\n\n%DESC%\n{} package\n\n%SIZE%\n1048576\n\n%REASON%\n{}\n\n%DEPENDS%\n{}\n\n",
+                pkg,
+                pkg,
+                if *explicit { 0 } else { 1 },
+                depends.join("\n")
+            );
+            fs::write(dir.join("desc"), desc).unwrap();
+        }
+
+        let db = LocalDb::load_from(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        db
+    }
+
+    /// A gzip-compressed sync database named `repo.db` listing `packages` as
+    /// (name, download size, installed size).
+    pub fn sync_db(name: &str, repo: &str, packages: &[(&str, u64, u64)]) -> SyncDb {
+        let packages: Vec<_> = packages.iter().map(|&(pkg, csize, isize)| (pkg, csize, isize, &[][..])).collect();
+        sync_db_with_groups(name, repo, &packages)
+    }
+
+    /// Like [`sync_db`], with the groups each package belongs to.
+    pub fn sync_db_with_groups(name: &str, repo: &str, packages: &[(&str, u64, u64, &[&str])]) -> SyncDb {
+        let root: PathBuf = env::temp_dir().join(format!("de-switcher-sync-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let file = fs::File::create(root.join(format!("{}.db", repo))).unwrap();
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+        for (pkg, csize, isize, groups) in packages {
+            let desc = format!(
+                "%NAME%\n{0}\n\n%VERSION%\n2.0-1\n\n%DESC%\n{0} from {1}\n\n%CSIZE%\n{2}\n\n%ISIZE%\n{3}\n\n%GROUPS%\n{4}\n\n",
+                pkg, repo, csize, isize, groups.join("\n")
+            );
+            let mut header = tar::Header::new_gnu();
+            header.set_size(desc.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, format!("{}-2.0-1/desc", pkg), desc.as_bytes()).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap();
+
+        let db = SyncDb::load_from(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{local_db, sync_db, sync_db_with_groups};
+    use super::*;
+
+    #[test]
+    fn sync_db_reads_gzip_archives() {
+        let db = sync_db("read", "extra", &[("gnome", 1000, 5000), ("gdm", 200, 800)]);
+
+        let gdm = db.get("gdm").unwrap();
+        assert_eq!(gdm.repository, "extra");
+        assert_eq!(gdm.version, "2.0-1");
+        assert_eq!((gdm.download_size, gdm.installed_size), (200, 800));
+        assert!(db.get("plasma-meta").is_none());
+    }
+
+    #[test]
+    fn inspector_prefers_local_metadata_and_lists_reverse_dependencies() {
+        let local = local_db("inspect", &[("qt6-base", false, &[]), ("kate", true, &["qt6-base"]), ("sddm", true, &["qt6-base"])]);
+        let sync = sync_db("inspect", "extra", &[("qt6-base", 100, 200), ("gnome", 1000, 5000)]);
+
+        let qt = package_info("qt6-base", &local, &sync).unwrap();
+        assert!(qt.installed);
+        assert_eq!(qt.version, "1.0-1");
+        assert_eq!(qt.installed_size, 1048576);
+        assert_eq!(qt.repository.as_deref(), Some("extra"));
+        assert_eq!(qt.required_by, ["kate", "sddm"]);
+
+        let gnome = package_info("gnome", &local, &sync).unwrap();
+        assert!(!gnome.installed);
+        assert_eq!(gnome.installed_size, 5000);
+        assert!(package_info("missing", &local, &sync).is_none());
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn local_db_reads_reason_and_versioned_dependencies() {
+        let db = local_db("reason", &[("kate", true, &["qt6-base>=6.5"]), ("qt6-base", false, &[])]);
+
+        assert!(db.get("kate").unwrap().explicit);
+        assert!(!db.get("qt6-base").unwrap().explicit);
+        assert_eq!(db.get("kate").unwrap().depends, ["qt6-base"]);
+        assert_eq!(db.required_by("qt6-base"), ["kate"]);
+        assert!(db.required_by("kate").is_empty());
+    }
+
+    #[test]
+    fn removal_set_skips_shared_and_uninstalled_packages() {
+        let db = local_db("removal-set", &[("plasma-meta", true, &[]), ("kate", true, &[]), ("qt6-base", false, &[])]);
+        let current = names(&["plasma-meta", "kate", "qt6-base", "sddm", "kate"]);
+        let target = names(&["gnome", "qt6-base"]);
+
+        assert_eq!(removal_set(&current, &target, &db), ["plasma-meta", "kate"]);
+        assert!(removal_set(&current, &current, &db).is_empty());
+        assert!(removal_set(&current, &target, &LocalDb::default()).is_empty());
+    }
+
+    #[test]
+    fn sync_db_lists_group_members() {
+        let db = sync_db_with_groups("group-members", "extra", &[
+            ("cosmic-comp", 1, 1, &["cosmic"]),
+            ("cosmic-applets", 1, 1, &["cosmic"]),
+            ("gnome-shell", 1, 1, &["gnome"]),
+        ]);
+
+        assert_eq!(db.group_members("cosmic"), ["cosmic-applets", "cosmic-comp"]);
+        assert!(db.group_members("kde-applications").is_empty());
+    }
+
+    #[test]
+    fn space_estimate_skips_installed_packages_and_reports_unknown_ones() {
+        let local = local_db("space-pure", &[("qt6-base", false, &[]), ("kate", true, &[])]);
+        let sync = sync_db("space-pure", "extra", &[("gnome", 1000, 5000), ("qt6-base", 50, 70)]);
+
+        let estimate = space_estimate(&names(&["gnome", "qt6-base", "cosmic"]), &names(&["kate", "missing"]), &local, &sync);
+        assert_eq!(estimate, SpaceEstimate { download: 1000, installed: 5000, freed: 1048576, unknown: names(&["cosmic"]) });
+    }
+
+    #[test]
+    fn protect_explicit_keeps_what_user_packages_depend_on() {
+        let db = local_db("protect", &[
+            ("plasma-meta", true, &["plasma-integration"]),
+            ("plasma-integration", false, &["qt6-base"]),
+            ("qt6-base", false, &[]),
+            ("kate", true, &[]),
+            ("firefox", true, &["plasma-integration"]),
+        ]);
+        let candidates = names(&["plasma-meta", "plasma-integration", "qt6-base", "kate"]);
+        let profile: HashSet<&str> = ["plasma-meta", "plasma-integration", "qt6-base", "kate"].into_iter().collect();
+
+        let plan = protect_explicit(candidates, &profile, &[], &db);
+        assert_eq!(plan.kept, ["firefox"]);
+        assert_eq!(plan.remove, ["plasma-meta", "kate"]);
+    }
+
+    #[test]
+    fn protect_explicit_ignores_profile_and_dependency_packages() {
+        let db = local_db("protect-profile", &[
+            ("plasma-meta", true, &["qt6-base"]),
+            ("qt6-base", false, &[]),
+            ("qt6-tools", false, &["qt6-base"]),
+        ]);
+        let candidates = names(&["plasma-meta", "qt6-base"]);
+        let profile: HashSet<&str> = ["plasma-meta", "qt6-base"].into_iter().collect();
+
+        let plan = protect_explicit(candidates.clone(), &profile, &[], &db);
+        assert!(plan.kept.is_empty());
+        assert_eq!(plan.remove, candidates);
+    }
+
+    #[test]
+    fn protect_explicit_never_keeps_a_profiles_own_meta_package() {
+        let db = local_db("protect-meta", &[
+            ("plasma-meta", true, &["plasma-desktop"]),
+            ("plasma-desktop", false, &[]),
+            ("firefox", true, &["plasma-desktop"]),
+        ]);
+        let candidates = names(&["plasma-desktop"]);
+        let profile: HashSet<&str> = ["plasma-desktop"].into_iter().collect();
+
+        let plan = protect_explicit(candidates.clone(), &profile, &["KDE-Desktop"], &db);
+        assert_eq!(plan.kept, ["firefox"]);
+        assert!(plan.remove.is_empty());
+
+        let db = local_db("protect-meta-only", &[("plasma-meta", true, &["plasma-desktop"]), ("plasma-desktop", false, &[])]);
+        let plan = protect_explicit(candidates.clone(), &profile, &["KDE-Desktop"], &db);
+        assert!(plan.kept.is_empty());
+        assert_eq!(plan.remove, candidates);
+        assert_eq!(protect_explicit(candidates, &profile, &[], &db).kept, ["plasma-meta"]);
+    }
+}
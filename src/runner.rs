@@ -0,0 +1,77 @@
+use std::io;
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+}
+
+/// Runs external programs such as `eos-packagelist`, so they can be replaced in tests.
+pub trait CommandRunner {
+    /// Runs `program` with `args`. Errors if the program cannot be started at all,
+    /// e.g. `io::ErrorKind::NotFound` when it is not installed.
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput>;
+}
+
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let output = Command::new(program).args(args).output()?;
+
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::{CommandOutput, CommandRunner};
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    /// Replays canned outputs for exact command lines. Anything not scripted behaves
+    /// like a missing binary.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        responses: Vec<(String, CommandOutput)>,
+        calls: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn new() -> Self {
+            ScriptedRunner::default()
+        }
+
+        pub fn with_output(mut self, command_line: &str, stdout: &str) -> Self {
+            self.responses.push((command_line.to_string(), CommandOutput { success: true, stdout: stdout.to_string() }));
+            self
+        }
+
+        pub fn with_failure(mut self, command_line: &str) -> Self {
+            self.responses.push((command_line.to_string(), CommandOutput { success: false, stdout: String::new() }));
+            self
+        }
+
+        /// Shared log of every command line run, still readable after the runner is boxed.
+        pub fn calls(&self) -> Rc<RefCell<Vec<String>>> {
+            Rc::clone(&self.calls)
+        }
+    }
+
+    impl CommandRunner for ScriptedRunner {
+        fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+            let command_line = std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+            self.calls.borrow_mut().push(command_line.clone());
+
+            self.responses.iter()
+                .find(|(line, _)| *line == command_line)
+                .map(|(_, output)| output.clone())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: command not found", program)))
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::pacman::SpaceEstimate;
+use crate::system::SnapshotTool;
+
+/// Minimum free space on `/` the pre-flight check requires before touching packages.
+pub const PREFLIGHT_MIN_FREE_MB: u64 = 2048;
+
+/// Free space the pre-flight check asks for: what the new packages download and
+/// install, but never less than [`PREFLIGHT_MIN_FREE_MB`].
+pub fn required_free_mb(estimate: &SpaceEstimate) -> u64 {
+    const MIB: u64 = 1024 * 1024;
+    (estimate.download + estimate.installed).div_ceil(MIB).max(PREFLIGHT_MIN_FREE_MB)
+}
+
+/// Formats a package list as the body of a bash array, one package per line.
+pub fn bash_array(packages: &[String]) -> String {
+    if packages.is_empty() {
+        return String::new();
+    }
+
+    let mut body = String::from("\n");
+    for pkg in packages {
+        body.push_str("    ");
+        body.push_str(pkg);
+        body.push('\n');
+    }
+    body
+}
+
+/// Checks run before any package is removed. Each one aborts the script on failure.
+pub fn preflight_section(min_free_mb: u64) -> String {
+    format!(
+        r#"# 0. PRE-FLIGHT CHECKS
+# The switch must not run inside the graphical session it is about to remove.
+
+preflight_fail() {{
+    echo "Pre-flight check failed: $1" >&2
+    echo "Aborting, nothing has been changed." >&2
+    exit 1
+}}
+
+if [ -n "$DISPLAY" ] || [ -n "$WAYLAND_DISPLAY" ]; then
+    preflight_fail "a graphical session is active. Log out and run this script from a TTY (e.g. Ctrl+Alt+F4)."
+fi
+
+if [ ! -t 0 ]; then
+    preflight_fail "not running on a terminal."
+fi
+
+case "$(tty)" in
+    /dev/tty[0-9]*) ;;
+    *)
+        if [ -z "$SSH_CONNECTION" ]; then
+            preflight_fail "not running on a virtual console TTY. Switch with Ctrl+Alt+F4 and log in there."
+        fi
+        ;;
+esac
+
+if [ -e /var/lib/pacman/db.lck ]; then
+    preflight_fail "the pacman database is locked (/var/lib/pacman/db.lck). Wait for other package operations to finish."
+fi
+
+REQUIRED_FREE_MB={min_free_mb}
+AVAILABLE_FREE_MB=$(df --output=avail -m / | tail -n 1 | tr -d ' ')
+if [ -z "$AVAILABLE_FREE_MB" ] || [ "$AVAILABLE_FREE_MB" -lt "$REQUIRED_FREE_MB" ]; then
+    preflight_fail "only ${{AVAILABLE_FREE_MB:-unknown}} MB free on /, at least $REQUIRED_FREE_MB MB required."
+fi
+
+if ! sudo -v; then
+    preflight_fail "the current user cannot run sudo."
+fi
+
+echo "Pre-flight checks passed."
+"#
+    )
+}
+
+/// Optional step taking a snapshot labelled `de-switcher: <action>` so the change can
+/// be rolled back.
+pub fn snapshot_section(tool: SnapshotTool, action: &str) -> String {
+    let description = format!("de-switcher: {}", action);
+
+    format!(
+        r#"# PRE-SWITCH SNAPSHOT ({tool})
+echo "Creating {tool} snapshot: {description}"
+if ! {command}; then
+    echo "Snapshot failed. Aborting, nothing has been changed." >&2
+    exit 1
+fi
+"#,
+        tool = tool.name(),
+        description = description,
+        command = tool.create_command(&description),
+    )
+}
+
+/// Saves the installed packages and enabled display manager next to the script,
+/// so the rollback script can restore them.
+pub fn record_state_section() -> String {
+    r#"# RECORD PRE-SWITCH STATE (used by the rollback script)
+STATE_DIR="$(cd "$(dirname "$0")" && pwd)/de_switch_script.sh.state"
+mkdir -p "$STATE_DIR"
+pacman -Qq | sort > "$STATE_DIR/installed-packages.txt"
+pacman -Qqe | sort > "$STATE_DIR/explicit-packages.txt"
+basename "$(readlink /etc/systemd/system/display-manager.service 2>/dev/null)" .service 2>/dev/null > "$STATE_DIR/display-manager.txt"
+echo "Pre-switch state recorded in $STATE_DIR"
+"#.to_string()
+}
@@ -0,0 +1,380 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::pacman::LocalDb;
+
+const MOUNTS_PATH: &str = "/proc/mounts";
+const DISPLAY_MANAGER_LINK: &str = "/etc/systemd/system/display-manager.service";
+const LOGIND_PATH: &str = "/usr/lib/systemd/systemd-logind";
+const SESSION_DIRS: &[&str] = &["/usr/share/xsessions", "/usr/share/wayland-sessions"];
+const SESSION_ENV_VARS: &[&str] = &["XDG_CURRENT_DESKTOP", "XDG_SESSION_DESKTOP", "DESKTOP_SESSION"];
+
+/// Packages whose presence indicates a DE profile is installed.
+const PROFILE_MARKER_PACKAGES: &[(&str, &str)] = &[
+    ("plasma-desktop", "KDE-Desktop"),
+    ("gnome-shell", "GNOME-Desktop"),
+    ("xfce4-session", "XFCE4-Desktop"),
+    ("cinnamon", "Cinnamon-Desktop"),
+    ("mate-session-manager", "MATE-Desktop"),
+    ("budgie-desktop", "Budgie-Desktop"),
+    ("lxqt-session", "LXQT-Desktop"),
+    ("lxsession", "LXDE-Desktop"),
+    ("i3-wm", "i3-Window-Manager"),
+    ("cosmic-session", "COSMIC-Desktop"),
+    ("hyprland", "Hyprland"),
+    ("sway", "Sway"),
+    ("niri", "niri"),
+    ("river", "river"),
+];
+
+/// Meta-packages pulling in a whole profile. Usually installed explicitly, but like the
+/// markers they belong to the profile rather than to the user.
+const PROFILE_META_PACKAGES: &[(&str, &str)] = &[
+    ("plasma-meta", "KDE-Desktop"),
+];
+
+pub const UNKNOWN_PROFILE: &str = "Unknown-Desktop";
+/// Chosen instead of a profile when nothing should be removed.
+pub const INSTALL_ONLY_PROFILE: &str = "None";
+
+/// One signal pointing at the running or installed DE.
+#[derive(Debug, Clone)]
+pub struct DeEvidence {
+    pub source: String,
+    pub value: String,
+    pub profile: String,
+}
+
+impl DeEvidence {
+    /// Environment variables describe the running session; session files and
+    /// packages only describe what is installed.
+    pub fn is_from_session(&self) -> bool {
+        SESSION_ENV_VARS.contains(&self.source.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotTool {
+    Snapper,
+    Timeshift,
+}
+
+impl SnapshotTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SnapshotTool::Snapper => "snapper",
+            SnapshotTool::Timeshift => "timeshift",
+        }
+    }
+
+    /// Shell command that takes a snapshot labelled with `description`.
+    pub fn create_command(&self, description: &str) -> String {
+        match self {
+            SnapshotTool::Snapper => format!(
+                "sudo snapper -c root create --type single --cleanup-algorithm number --description \"{}\"",
+                description
+            ),
+            SnapshotTool::Timeshift => format!(
+                "sudo timeshift --create --tags O --comments \"{}\"",
+                description
+            ),
+        }
+    }
+}
+
+/// Reads the parts of the running system the app looks at, so they can be replaced in tests.
+pub trait SystemProbe {
+    fn env_var(&self, name: &str) -> Option<String>;
+    /// Names of the `.desktop` session files in `dir`, without the extension, sorted.
+    fn session_files(&self, dir: &str) -> Vec<String>;
+    /// Filesystem type mounted at `/`.
+    fn root_filesystem(&self) -> Option<String>;
+    /// The display manager unit `display-manager.service` is aliased to, e.g. `sddm`.
+    fn current_display_manager(&self) -> Option<String>;
+    /// Whether systemd-logind is installed, which GDM needs to manage seats.
+    fn has_logind(&self) -> bool;
+}
+
+pub struct HostProbe;
+
+impl SystemProbe for HostProbe {
+    fn env_var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+
+    fn session_files(&self, dir: &str) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let path = e.path();
+                if path.extension()? != "desktop" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().to_string())
+            })
+            .collect();
+        sessions.sort();
+        sessions
+    }
+
+    /// Taken from the last matching entry in `/proc/mounts`.
+    fn root_filesystem(&self) -> Option<String> {
+        let mounts = fs::read_to_string(MOUNTS_PATH).ok()?;
+
+        mounts.lines()
+            .rev()
+            .find_map(|line| {
+                let mut fields = line.split_whitespace();
+                let _device = fields.next()?;
+                let mount_point = fields.next()?;
+                let fs_type = fields.next()?;
+                (mount_point == "/").then(|| fs_type.to_string())
+            })
+    }
+
+    /// `systemctl enable` creates the alias symlink, so it exists whether or not the
+    /// unit is running.
+    fn current_display_manager(&self) -> Option<String> {
+        let target = fs::read_link(DISPLAY_MANAGER_LINK).ok()?;
+        let unit = target.file_name()?.to_str()?;
+        Some(unit.strip_suffix(".service").unwrap_or(unit).to_string())
+    }
+
+    fn has_logind(&self) -> bool {
+        Path::new(LOGIND_PATH).exists()
+    }
+}
+
+/// Snapper is only used on a btrfs root. Timeshift also works in rsync mode, so it is
+/// accepted on any filesystem.
+pub fn detect_snapshot_tool(probe: &dyn SystemProbe, db: &LocalDb) -> Option<SnapshotTool> {
+    let root_is_btrfs = probe.root_filesystem().as_deref() == Some("btrfs");
+
+    if root_is_btrfs && db.is_installed("snapper") {
+        Some(SnapshotTool::Snapper)
+    } else if db.is_installed("timeshift") {
+        Some(SnapshotTool::Timeshift)
+    } else {
+        None
+    }
+}
+
+/// The package whose presence shows `profile` is installed, e.g. `plasma-desktop`.
+pub fn profile_marker(profile: &str) -> Option<&'static str> {
+    PROFILE_MARKER_PACKAGES.iter()
+        .find(|(_, p)| *p == profile)
+        .map(|(package, _)| *package)
+}
+
+/// The marker and meta-packages of `profile`, e.g. `plasma-desktop` and `plasma-meta`.
+pub fn profile_own_packages(profile: &str) -> impl Iterator<Item = &'static str> + '_ {
+    PROFILE_MARKER_PACKAGES.iter()
+        .chain(PROFILE_META_PACKAGES)
+        .filter(move |(_, p)| *p == profile)
+        .map(|(package, _)| *package)
+}
+
+/// Gathers every signal about the current DE: session environment variables, installed
+/// session files and installed meta-packages. `map_profile` turns a raw name into a profile.
+pub fn collect_de_evidence<F>(probe: &dyn SystemProbe, db: &LocalDb, map_profile: F) -> Vec<DeEvidence>
+where
+    F: Fn(&str) -> String,
+{
+    let mut evidence = Vec::new();
+
+    for var in SESSION_ENV_VARS {
+        let Some(value) = probe.env_var(var) else {
+            continue;
+        };
+        // DESKTOP_SESSION may be a path such as `/usr/share/xsessions/plasma`.
+        let value = value.rsplit('/').next().unwrap_or_default().trim();
+        if !value.is_empty() {
+            evidence.push(DeEvidence {
+                source: var.to_string(),
+                value: value.to_string(),
+                profile: map_profile(value),
+            });
+        }
+    }
+
+    for dir in SESSION_DIRS {
+        let source = Path::new(dir).file_name().map_or(dir.to_string(), |n| n.to_string_lossy().to_string());
+        for session in probe.session_files(dir) {
+            evidence.push(DeEvidence {
+                source: source.clone(),
+                profile: map_profile(&session),
+                value: session,
+            });
+        }
+    }
+
+    for (package, profile) in PROFILE_MARKER_PACKAGES {
+        if db.is_installed(package) {
+            evidence.push(DeEvidence {
+                source: "package".to_string(),
+                value: package.to_string(),
+                profile: profile.to_string(),
+            });
+        }
+    }
+
+    evidence
+}
+
+/// Picks the profile the evidence points to. A running session wins; otherwise the
+/// profile with the most installed signals, earliest first on a tie.
+pub fn pick_de_profile(evidence: &[DeEvidence]) -> Option<&DeEvidence> {
+    let known = || evidence.iter().filter(|e| e.profile != UNKNOWN_PROFILE);
+
+    if let Some(session) = known().find(|e| e.is_from_session()) {
+        return Some(session);
+    }
+
+    let count = |profile: &str| known().filter(|e| e.profile == profile).count();
+    known().fold(None, |best: Option<&DeEvidence>, e| match best {
+        Some(b) if count(&b.profile) >= count(&e.profile) => Some(b),
+        _ => Some(e),
+    })
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::SystemProbe;
+
+    /// A system described up front. Anything not set is absent: no session, no
+    /// display manager, no logind.
+    #[derive(Default)]
+    pub struct FakeProbe {
+        env: Vec<(String, String)>,
+        sessions: Vec<(String, Vec<String>)>,
+        root_filesystem: Option<String>,
+        display_manager: Option<String>,
+        logind: bool,
+    }
+
+    impl FakeProbe {
+        pub fn new() -> Self {
+            FakeProbe::default()
+        }
+
+        pub fn with_env(mut self, name: &str, value: &str) -> Self {
+            self.env.push((name.to_string(), value.to_string()));
+            self
+        }
+
+        pub fn with_sessions(mut self, dir: &str, sessions: &[&str]) -> Self {
+            self.sessions.push((dir.to_string(), sessions.iter().map(|s| s.to_string()).collect()));
+            self
+        }
+
+        pub fn with_root_filesystem(mut self, fs_type: &str) -> Self {
+            self.root_filesystem = Some(fs_type.to_string());
+            self
+        }
+
+        pub fn with_display_manager(mut self, dm: &str) -> Self {
+            self.display_manager = Some(dm.to_string());
+            self
+        }
+
+        pub fn with_logind(mut self) -> Self {
+            self.logind = true;
+            self
+        }
+    }
+
+    impl SystemProbe for FakeProbe {
+        fn env_var(&self, name: &str) -> Option<String> {
+            self.env.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+        }
+
+        fn session_files(&self, dir: &str) -> Vec<String> {
+            self.sessions.iter().find(|(d, _)| d == dir).map(|(_, s)| s.clone()).unwrap_or_default()
+        }
+
+        fn root_filesystem(&self) -> Option<String> {
+            self.root_filesystem.clone()
+        }
+
+        fn current_display_manager(&self) -> Option<String> {
+            self.display_manager.clone()
+        }
+
+        fn has_logind(&self) -> bool {
+            self.logind
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeProbe;
+    use super::*;
+    use crate::pacman::testing::local_db;
+
+    fn evidence(source: &str, profile: &str) -> DeEvidence {
+        DeEvidence { source: source.to_string(), value: profile.to_lowercase(), profile: profile.to_string() }
+    }
+
+    #[test]
+    fn running_session_wins_over_installed_signals() {
+        let found = [
+            evidence("package", "KDE-Desktop"),
+            evidence("xsessions", "KDE-Desktop"),
+            evidence("XDG_CURRENT_DESKTOP", "GNOME-Desktop"),
+        ];
+        assert_eq!(pick_de_profile(&found).unwrap().profile, "GNOME-Desktop");
+    }
+
+    #[test]
+    fn most_installed_signals_win_and_ties_go_to_the_first() {
+        let found = [
+            evidence("package", "XFCE4-Desktop"),
+            evidence("package", "KDE-Desktop"),
+            evidence("xsessions", "KDE-Desktop"),
+        ];
+        assert_eq!(pick_de_profile(&found).unwrap().profile, "KDE-Desktop");
+
+        let tied = [evidence("package", "XFCE4-Desktop"), evidence("package", "KDE-Desktop")];
+        assert_eq!(pick_de_profile(&tied).unwrap().profile, "XFCE4-Desktop");
+    }
+
+    #[test]
+    fn unknown_profiles_are_never_picked() {
+        let found = [evidence("XDG_CURRENT_DESKTOP", UNKNOWN_PROFILE), evidence("package", "Sway")];
+        assert_eq!(pick_de_profile(&found).unwrap().profile, "Sway");
+        assert!(pick_de_profile(&[evidence("DESKTOP_SESSION", UNKNOWN_PROFILE)]).is_none());
+        assert!(pick_de_profile(&[]).is_none());
+    }
+
+    #[test]
+    fn evidence_comes_from_the_probe_and_the_local_db() {
+        let probe = FakeProbe::new()
+            .with_env("DESKTOP_SESSION", "/usr/share/xsessions/plasma")
+            .with_sessions("/usr/share/wayland-sessions", &["plasma", "sway"]);
+        let db = local_db("evidence", &[("gnome-shell", true, &[])]);
+
+        let found = collect_de_evidence(&probe, &db, |raw| raw.to_uppercase());
+        let found: Vec<_> = found.iter().map(|e| (e.source.as_str(), e.value.as_str(), e.profile.as_str())).collect();
+        assert_eq!(found, [
+            ("DESKTOP_SESSION", "plasma", "PLASMA"),
+            ("wayland-sessions", "plasma", "PLASMA"),
+            ("wayland-sessions", "sway", "SWAY"),
+            ("package", "gnome-shell", "GNOME-Desktop"),
+        ]);
+    }
+
+    #[test]
+    fn snapper_needs_a_btrfs_root() {
+        let db = local_db("snapshot-tool", &[("snapper", true, &[]), ("timeshift", true, &[])]);
+        let btrfs = FakeProbe::new().with_root_filesystem("btrfs");
+
+        assert_eq!(detect_snapshot_tool(&btrfs, &db), Some(SnapshotTool::Snapper));
+        assert_eq!(detect_snapshot_tool(&FakeProbe::new().with_root_filesystem("ext4"), &db), Some(SnapshotTool::Timeshift));
+        assert_eq!(detect_snapshot_tool(&btrfs, &LocalDb::default()), None);
+    }
+}